@@ -0,0 +1,172 @@
+use kube::client::AuthError;
+use kube::config::{KubeconfigError, LoadDataError};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Error returned to the client by backend commands
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type", content = "info")]
+pub(crate) enum CommandError {
+    /// No context has been selected with `start` yet
+    NotConnected,
+    /// `list_kube_contexts` hasn't been called yet
+    KubeconfigNotLoaded,
+    /// The context, or the cluster or user it refers to, isn't in the kubeconfig
+    ContextNotFound {
+        message: String
+    },
+    /// The kubeconfig entries for the context are malformed
+    KubeconfigInvalid {
+        message: String
+    },
+    /// A file referenced by the kubeconfig (certificate, key, token) couldn't be read
+    MissingFile {
+        path: String,
+        message: String
+    },
+    /// The exec credential plugin couldn't be started, failed, or printed something unexpected
+    ExecPlugin {
+        command: Option<String>,
+        exit_code: Option<i32>,
+        stderr: Option<String>,
+        message: String
+    },
+    /// Any other authentication failure (bad token, OIDC, ...)
+    Auth {
+        message: String
+    },
+    /// The kubeconfig was loaded but a client couldn't be built from it (TLS setup, ...)
+    ClientConstruction {
+        message: String
+    },
+    /// The proxy for the context is misconfigured,
+    /// or the API server couldn't be reached through it
+    Proxy {
+        proxy_url: String,
+        message: String
+    },
+    /// The API server answered with an error status
+    Api {
+        code: u16,
+        reason: String,
+        message: String
+    },
+    /// A sort path or filter expression couldn't be parsed
+    InvalidQuery {
+        message: String
+    },
+    /// There's no subscription with this ID
+    SubscriptionNotFound {
+        id: i32
+    },
+    /// The user didn't allow a kubeconfig script source to run its command
+    ScriptNotConfirmed {
+        command: String
+    },
+    Other {
+        message: String
+    },
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::NotConnected => write!(f, "not connected to a cluster"),
+            CommandError::KubeconfigNotLoaded => write!(f, "kubeconfig not ready"),
+            CommandError::ContextNotFound { message } => write!(f, "{}", message),
+            CommandError::KubeconfigInvalid { message } => write!(f, "invalid kubeconfig: {}", message),
+            CommandError::MissingFile { path, message } => write!(f, "can't read {}: {}", path, message),
+            CommandError::ExecPlugin { message, .. } => write!(f, "exec plugin failed: {}", message),
+            CommandError::Auth { message } => write!(f, "authentication failed: {}", message),
+            CommandError::ClientConstruction { message } => write!(f, "can't create client: {}", message),
+            CommandError::Proxy { proxy_url, message } => write!(f, "proxy {}: {}", proxy_url, message),
+            CommandError::Api { code, message, .. } => write!(f, "{} ({})", message, code),
+            CommandError::InvalidQuery { message } => write!(f, "invalid query: {}", message),
+            CommandError::SubscriptionNotFound { id } => write!(f, "no subscription {}", id),
+            CommandError::ScriptNotConfirmed { command } => write!(f, "not allowed to run {}", command),
+            CommandError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Other { message }
+    }
+}
+
+impl From<LoadDataError> for CommandError {
+    fn from(e: LoadDataError) -> Self {
+        match &e {
+            LoadDataError::ReadFile(io, path) => CommandError::MissingFile {
+                path: path.display().to_string(),
+                message: io.to_string(),
+            },
+            _ => CommandError::KubeconfigInvalid { message: e.to_string() },
+        }
+    }
+}
+
+impl From<KubeconfigError> for CommandError {
+    fn from(e: KubeconfigError) -> Self {
+        match e {
+            KubeconfigError::LoadContext(_)
+            | KubeconfigError::LoadClusterOfContext(_)
+            | KubeconfigError::CurrentContextNotSet => CommandError::ContextNotFound { message: e.to_string() },
+            KubeconfigError::ReadConfig(io, path) => CommandError::MissingFile {
+                path: path.display().to_string(),
+                message: io.to_string(),
+            },
+            KubeconfigError::LoadCertificateAuthority(inner)
+            | KubeconfigError::LoadClientCertificate(inner)
+            | KubeconfigError::LoadClientKey(inner) => inner.into(),
+            _ => CommandError::KubeconfigInvalid { message: e.to_string() },
+        }
+    }
+}
+
+impl From<AuthError> for CommandError {
+    fn from(e: AuthError) -> Self {
+        match &e {
+            AuthError::AuthExecRun { cmd, status, out } => CommandError::ExecPlugin {
+                command: Some(cmd.clone()),
+                exit_code: status.code(),
+                stderr: Some(String::from_utf8_lossy(&out.stderr).trim().to_string()),
+                message: e.to_string(),
+            },
+            AuthError::AuthExecStart(_)
+            | AuthError::AuthExecParse(_)
+            | AuthError::AuthExec(_)
+            | AuthError::ExecPluginFailed
+            | AuthError::MissingCommand
+            | AuthError::ExecMissingClusterInfo => CommandError::ExecPlugin {
+                command: None,
+                exit_code: None,
+                stderr: None,
+                message: e.to_string(),
+            },
+            AuthError::ReadTokenFile(io, path) => CommandError::MissingFile {
+                path: path.display().to_string(),
+                message: io.to_string(),
+            },
+            _ => CommandError::Auth { message: e.to_string() },
+        }
+    }
+}
+
+impl From<kube::Error> for CommandError {
+    fn from(e: kube::Error) -> Self {
+        match e {
+            kube::Error::Api(response) => CommandError::Api {
+                code: response.code,
+                reason: response.reason.clone(),
+                message: response.message.clone(),
+            },
+            kube::Error::Auth(inner) => inner.into(),
+            kube::Error::InferConfig(_) | kube::Error::TlsRequired | kube::Error::RustlsTls(_) => {
+                CommandError::ClientConstruction { message: e.to_string() }
+            }
+            _ => CommandError::Other { message: e.to_string() },
+        }
+    }
+}
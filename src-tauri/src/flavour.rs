@@ -0,0 +1,254 @@
+use crate::kubeconfig::KubeConfigInfo;
+use crate::proxy::{self, ProxySettings};
+use kube::config::{AuthInfo, KubeConfigOptions, Kubeconfig};
+use kube::Config;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long we wait for `/version` per context while listing contexts
+const VERSION_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long a server's `/version` is reused before listing contexts asks again
+const VERSION_TTL: Duration = Duration::from_secs(10 * 60);
+/// Servers that didn't answer are asked again sooner
+const VERSION_FAILURE_TTL: Duration = Duration::from_secs(60);
+
+/// Context name and server URL
+type VersionKey = (String, Option<String>);
+
+/// `/version` answers by context and server, so listing or reloading contexts doesn't ask every cluster every time
+#[derive(Default)]
+pub(crate) struct ServerVersions(Mutex<HashMap<VersionKey, (Option<String>, Instant)>>);
+
+impl ServerVersions {
+    fn get(&self, key: &VersionKey) -> Option<Option<String>> {
+        let versions = self.0.lock().ok()?;
+        let (version, fetched) = versions.get(key)?;
+        let ttl = if version.is_some() { VERSION_TTL } else { VERSION_FAILURE_TTL };
+        (fetched.elapsed() < ttl).then(|| version.clone())
+    }
+
+    fn insert(&self, key: VersionKey, version: Option<String>) {
+        if let Ok(mut versions) = self.0.lock() {
+            versions.retain(|_, (_, fetched)| fetched.elapsed() < VERSION_TTL);
+            versions.insert(key, (version, Instant::now()));
+        }
+    }
+
+    /// Forgets every answer, e.g. when the proxies change
+    pub(crate) fn clear(&self) {
+        if let Ok(mut versions) = self.0.lock() {
+            versions.clear();
+        }
+    }
+}
+
+/// The kind of cluster a context points at, used by the UI for badges and grouping
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ClusterFlavour {
+    Eks,
+    Gke,
+    Aks,
+    OpenShift,
+    K3s,
+    Kind,
+    Minikube,
+    Generic,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ContextDetails {
+    pub(crate) name: String,
+    pub(crate) flavour: ClusterFlavour,
+    pub(crate) server: Option<String>,
+    /// gitVersion from `/version`, if the server answered
+    pub(crate) server_version: Option<String>,
+}
+
+/// Everything we know about a context without talking to the cluster
+struct ContextHints<'a> {
+    context_name: &'a str,
+    cluster_name: &'a str,
+    server: Option<&'a str>,
+    exec_command: Option<&'a str>,
+    exec_args: &'a [String],
+    certificate_authority: Option<&'a str>,
+}
+
+/// Entries are looked up by name, and the first one wins, as in kubeconfig merging and kube's context loading
+fn hints<'a>(kubeconfig: &'a Kubeconfig, context_name: &'a str) -> ContextHints<'a> {
+    let context = kubeconfig.contexts.iter()
+        .find(|c| c.name == context_name)
+        .and_then(|c| c.context.as_ref());
+    let cluster = context
+        .and_then(|c| kubeconfig.clusters.iter().find(|cl| cl.name == c.cluster))
+        .and_then(|cl| cl.cluster.as_ref());
+    let exec = context
+        .and_then(|c| c.user.as_ref())
+        .and_then(|u| kubeconfig.auth_infos.iter().find(|a| &a.name == u))
+        .and_then(|a| a.auth_info.as_ref())
+        .and_then(|a| a.exec.as_ref());
+
+    ContextHints {
+        context_name,
+        cluster_name: context.map(|c| c.cluster.as_str()).unwrap_or_default(),
+        server: cluster.and_then(|c| c.server.as_deref()),
+        exec_command: exec.and_then(|e| e.command.as_deref()),
+        exec_args: exec.and_then(|e| e.args.as_deref()).unwrap_or_default(),
+        certificate_authority: cluster.and_then(|c| c.certificate_authority.as_deref()),
+    }
+}
+
+/// Classifies a cluster from its kubeconfig entries and (optionally) its `/version` gitVersion
+fn classify(hints: &ContextHints, git_version: Option<&str>) -> ClusterFlavour {
+    let server = hints.server.unwrap_or_default();
+    let command = hints.exec_command
+        .map(|c| c.rsplit(['/', '\\']).next().unwrap_or(c))
+        .unwrap_or_default();
+    let git_version = git_version.unwrap_or_default();
+
+    if server.contains(".eks.amazonaws.com")
+        || command.starts_with("aws-iam-authenticator")
+        || (command.starts_with("aws") && hints.exec_args.iter().any(|a| a == "eks"))
+        || git_version.contains("-eks-") {
+        return ClusterFlavour::Eks;
+    }
+    if command.starts_with("gke-gcloud-auth-plugin") || git_version.contains("-gke.") {
+        return ClusterFlavour::Gke;
+    }
+    if server.contains(".azmk8s.io") || command.starts_with("kubelogin") {
+        return ClusterFlavour::Aks;
+    }
+    // `oc login` names contexts "<namespace>/<api-server-host>:<port>/<user>"
+    let oc_context = hints.context_name.split('/').count() == 3 && hints.cluster_name.contains(':');
+    if server.contains("openshift") || oc_context {
+        return ClusterFlavour::OpenShift;
+    }
+    if git_version.contains("+k3s") {
+        return ClusterFlavour::K3s;
+    }
+    if hints.context_name.starts_with("kind-") && hints.cluster_name.starts_with("kind-") {
+        return ClusterFlavour::Kind;
+    }
+    if hints.context_name == "minikube" || hints.certificate_authority.is_some_and(|ca| ca.contains(".minikube")) {
+        return ClusterFlavour::Minikube;
+    }
+    ClusterFlavour::Generic
+}
+
+/// Fetches `/version` anonymously, so listing contexts never triggers exec plugins or SSO logins.
+/// `/version` is readable without credentials on almost every cluster.
+async fn fetch_git_version(kubeconfig: &Kubeconfig, context_name: &str, proxy_settings: &ProxySettings) -> Option<String> {
+    let mut config = Config::from_custom_kubeconfig(kubeconfig.clone(), &KubeConfigOptions {
+        context: Some(context_name.to_string()),
+        cluster: None,
+        user: None,
+    }).await.ok()?;
+    config.auth_info = AuthInfo::default();
+    config.connect_timeout = Some(VERSION_TIMEOUT);
+    config.read_timeout = Some(VERSION_TIMEOUT);
+    proxy_settings.apply(context_name, &mut config).ok()?;
+
+    let client = proxy::client(config).ok()?;
+    let info = tokio::time::timeout(VERSION_TIMEOUT, client.apiserver_version()).await.ok()?.ok()?;
+    Some(info.git_version)
+}
+
+/// Fills in the details of every context in every source.
+/// A context defined more than once is described by its first definition, the one that connecting uses,
+/// and its server is only asked for `/version` when `versions` has no recent answer.
+pub(crate) async fn describe_contexts(kci: &mut KubeConfigInfo, proxy_settings: &ProxySettings, versions: &ServerVersions) {
+    let Some(kubeconfig) = &kci.merged else { return };
+
+    let mut described = HashSet::new();
+    let lookups = kubeconfig.contexts.iter().filter(|c| described.insert(c.name.as_str())).map(|c| async move {
+        let hints = hints(kubeconfig, &c.name);
+        let key = (c.name.clone(), hints.server.map(str::to_string));
+        let git_version = match versions.get(&key) {
+            Some(version) => version,
+            None => {
+                let version = fetch_git_version(kubeconfig, &c.name, proxy_settings).await;
+                versions.insert(key, version.clone());
+                version
+            }
+        };
+        let details = ContextDetails {
+            name: c.name.clone(),
+            flavour: classify(&hints, git_version.as_deref()),
+            server: hints.server.map(|s| s.to_string()),
+            server_version: git_version,
+        };
+        (c.name.clone(), details)
+    });
+    let details: HashMap<String, ContextDetails> = futures_util::future::join_all(lookups).await.into_iter().collect();
+
+    for source in kci.sources.iter_mut() {
+        source.details = source.contexts.iter()
+            .filter_map(|name| details.get(name).cloned())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hints<'a>(context_name: &'a str, cluster_name: &'a str, server: &'a str) -> ContextHints<'a> {
+        ContextHints {
+            context_name,
+            cluster_name,
+            server: Some(server),
+            exec_command: None,
+            exec_args: &[],
+            certificate_authority: None,
+        }
+    }
+
+    #[test]
+    fn classifies_managed_clusters() {
+        let eks = hints("prod", "prod", "https://ABC123.gr7.eu-west-1.eks.amazonaws.com");
+        assert_eq!(classify(&eks, None), ClusterFlavour::Eks);
+
+        let args = ["eks".to_string(), "get-token".to_string()];
+        let aws_exec = ContextHints { exec_command: Some("/usr/local/bin/aws"), exec_args: &args, ..hints("prod", "prod", "https://10.0.0.1") };
+        assert_eq!(classify(&aws_exec, None), ClusterFlavour::Eks);
+
+        let gke = ContextHints { exec_command: Some("gke-gcloud-auth-plugin"), ..hints("gke_project_zone_name", "gke_project_zone_name", "https://34.1.2.3") };
+        assert_eq!(classify(&gke, None), ClusterFlavour::Gke);
+        assert_eq!(classify(&hints("gke", "gke", "https://34.1.2.3"), Some("v1.30.5-gke.1014001")), ClusterFlavour::Gke);
+
+        let aks = hints("aks", "aks", "https://aks-dns-12345.hcp.westeurope.azmk8s.io:443");
+        assert_eq!(classify(&aks, None), ClusterFlavour::Aks);
+        let kubelogin = ContextHints { exec_command: Some("C:\\tools\\kubelogin.exe"), ..hints("aks", "aks", "https://10.0.0.1") };
+        assert_eq!(classify(&kubelogin, None), ClusterFlavour::Aks);
+    }
+
+    #[test]
+    fn classifies_openshift_by_server_or_oc_context_name() {
+        assert_eq!(classify(&hints("ocp", "ocp", "https://api.openshift.example.com:6443"), None), ClusterFlavour::OpenShift);
+        let oc_login = hints("default/api-crc-testing:6443/kubeadmin", "api-crc-testing:6443", "https://api.crc.testing:6443");
+        assert_eq!(classify(&oc_login, None), ClusterFlavour::OpenShift);
+    }
+
+    #[test]
+    fn classifies_local_clusters() {
+        assert_eq!(classify(&hints("default", "default", "https://127.0.0.1:6443"), Some("v1.31.4+k3s1")), ClusterFlavour::K3s);
+        assert_eq!(classify(&hints("kind-dev", "kind-dev", "https://127.0.0.1:40000"), None), ClusterFlavour::Kind);
+        // Only kind names both the context and the cluster kind-*
+        assert_eq!(classify(&hints("kind-dev", "dev", "https://127.0.0.1:40000"), None), ClusterFlavour::Generic);
+        assert_eq!(classify(&hints("minikube", "minikube", "https://192.168.49.2:8443"), None), ClusterFlavour::Minikube);
+        let minikube_ca = ContextHints { certificate_authority: Some("/home/me/.minikube/ca.crt"), ..hints("dev", "dev", "https://192.168.49.2:8443") };
+        assert_eq!(classify(&minikube_ca, None), ClusterFlavour::Minikube);
+    }
+
+    #[test]
+    fn managed_clusters_win_over_local_hints() {
+        // The version decides when the kubeconfig doesn't
+        assert_eq!(classify(&hints("kind-dev", "kind-dev", "https://127.0.0.1:40000"), Some("v1.29.8-eks-a737599")), ClusterFlavour::Eks);
+        assert_eq!(classify(&hints("cluster", "cluster", "https://10.0.0.1"), Some("v1.31.0")), ClusterFlavour::Generic);
+        assert_eq!(classify(&ContextHints { server: None, ..hints("cluster", "cluster", "") }, None), ClusterFlavour::Generic);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{proxy, ResourceListenEvent};
+use kube::runtime::watcher;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// What a shared watcher is doing, for "live / stale / reconnecting" indicators
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "state", content = "info")]
+pub(crate) enum WatchState {
+    /// Listing from scratch, on start or after the watch was lost
+    Relisting,
+    /// Listed and receiving events
+    Connected,
+    /// A request failed, the next attempt is in `retry_in_ms`
+    BackingOff {
+        retry_in_ms: u64,
+        message: String,
+    },
+    /// Connecting through the context's proxy failed, the next attempt is in `retry_in_ms`
+    ProxyFailed {
+        proxy_url: String,
+        retry_in_ms: u64,
+        message: String,
+    },
+    /// The watch expired (410 Gone), changes may have been missed until the relist finishes
+    Desynced,
+    /// The API server refused the list or watch (401/403), retrying in case permissions change
+    Forbidden {
+        message: String,
+    },
+}
+
+/// The HTTP status of a failed list or watch, if the API server answered
+fn status_code(e: &watcher::Error) -> Option<u16> {
+    match e {
+        watcher::Error::InitialListFailed(kube::Error::Api(response))
+        | watcher::Error::WatchStartFailed(kube::Error::Api(response))
+        | watcher::Error::WatchFailed(kube::Error::Api(response)) => Some(response.code),
+        watcher::Error::WatchError(response) => Some(response.code),
+        _ => None,
+    }
+}
+
+/// The request that failed behind a watcher error, if there was one
+fn request_error(e: &watcher::Error) -> Option<&kube::Error> {
+    match e {
+        watcher::Error::InitialListFailed(e) | watcher::Error::WatchStartFailed(e) | watcher::Error::WatchFailed(e) => Some(e),
+        _ => None,
+    }
+}
+
+/// Blames the proxy when `request` couldn't connect through it, like [proxy::request_error] does for commands
+fn backing_off(message: String, request: Option<&kube::Error>, retry_in: Duration, proxy_url: Option<&String>) -> WatchState {
+    let retry_in_ms = retry_in.as_millis() as u64;
+    match proxy_url {
+        Some(proxy_url) if request.is_some_and(proxy::is_connect_error) => {
+            WatchState::ProxyFailed { proxy_url: proxy_url.clone(), retry_in_ms, message }
+        }
+        _ => WatchState::BackingOff { retry_in_ms, message },
+    }
+}
+
+/// The state after a watcher error, when the next attempt is `retry_in` away
+pub(crate) fn state_for_error(e: &watcher::Error, retry_in: Duration, proxy_url: Option<&String>) -> WatchState {
+    match status_code(e) {
+        Some(410) => WatchState::Desynced,
+        Some(401) | Some(403) => WatchState::Forbidden { message: e.to_string() },
+        _ => backing_off(e.to_string(), request_error(e), retry_in, proxy_url),
+    }
+}
+
+/// [state_for_error] for a failed list or watch request made without a watcher
+pub(crate) fn state_for_request_error(e: &kube::Error, retry_in: Duration, proxy_url: Option<&String>) -> WatchState {
+    match e {
+        kube::Error::Api(response) if response.code == 401 || response.code == 403 => WatchState::Forbidden { message: e.to_string() },
+        _ => backing_off(e.to_string(), Some(e), retry_in, proxy_url),
+    }
+}
+
+/// Sends a watcher's state to its bridges when it changes,
+/// and keeps it for bridges that join later
+#[derive(Clone)]
+pub(crate) struct HealthReporter {
+    tx: tokio::sync::broadcast::Sender<ResourceListenEvent>,
+    state: Arc<RwLock<WatchState>>,
+    /// The proxy the watcher's requests go through, if any
+    proxy_url: Option<String>,
+}
+
+impl HealthReporter {
+    pub(crate) fn new(tx: tokio::sync::broadcast::Sender<ResourceListenEvent>, state: Arc<RwLock<WatchState>>, proxy_url: Option<String>) -> Self {
+        HealthReporter { tx, state, proxy_url }
+    }
+
+    /// Reports a watcher error, see [state_for_error]
+    pub(crate) fn watch_failed(&self, e: &watcher::Error, retry_in: Duration) {
+        self.set(state_for_error(e, retry_in, self.proxy_url.as_ref()));
+    }
+
+    /// Reports a failed list or watch request, see [state_for_request_error]
+    pub(crate) fn request_failed(&self, e: &kube::Error, retry_in: Duration) {
+        self.set(state_for_request_error(e, retry_in, self.proxy_url.as_ref()));
+    }
+
+    pub(crate) fn set(&self, state: WatchState) {
+        let Ok(mut current) = self.state.write() else { return };
+        if *current != state {
+            *current = state.clone();
+            let _ = self.tx.send(ResourceListenEvent::State { state });
+        }
+    }
+
+    /// Marks the watcher connected, unless it's in the middle of a relist
+    pub(crate) fn connected(&self) {
+        if self.state.read().is_ok_and(|s| *s != WatchState::Relisting) {
+            self.set(WatchState::Connected);
+        }
+    }
+}
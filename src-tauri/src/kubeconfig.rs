@@ -0,0 +1,437 @@
+use kube::config::{Kubeconfig, KubeconfigError};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use crate::flavour::ContextDetails;
+use crate::settings;
+
+/// Name of the file (inside the app config directory) that lists extra kubeconfig sources
+const SOURCE_SETTINGS_FILE: &str = "kubeconfig-sources.json";
+
+/// How long a script source's kubeconfig is reused before discovery runs the script again
+const SCRIPT_OUTPUT_TTL: Duration = Duration::from_secs(5 * 60);
+
+fn default_script_timeout_secs() -> u64 {
+    30
+}
+
+/// A user-defined command whose stdout is a kubeconfig YAML document
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ScriptSourceConfig {
+    pub(crate) name: String,
+    pub(crate) command: String,
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+    #[serde(default = "default_script_timeout_secs")]
+    pub(crate) timeout_secs: u64,
+}
+
+impl ScriptSourceConfig {
+    /// The command and its arguments, as shown to the user
+    pub(crate) fn command_line(&self) -> String {
+        std::iter::once(&self.command).chain(&self.args).cloned().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Extra kubeconfig sources configured by the user,
+/// on top of the KUBECONFIG env var / default path
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SourceSettings {
+    #[serde(default)]
+    pub(crate) scripts: Vec<ScriptSourceConfig>,
+    /// Individual kubeconfig files
+    #[serde(default)]
+    pub(crate) files: Vec<PathBuf>,
+    /// Directories where every file is a separate kubeconfig (e.g. ~/.kube/configs)
+    #[serde(default)]
+    pub(crate) directories: Vec<PathBuf>,
+}
+
+impl SourceSettings {
+    pub(crate) fn load(app: &AppHandle) -> SourceSettings {
+        settings::load(app, SOURCE_SETTINGS_FILE)
+    }
+
+    pub(crate) fn save(&self, app: &AppHandle) -> Result<(), String> {
+        settings::save(app, SOURCE_SETTINGS_FILE, self)
+    }
+
+    /// The scripts that would run commands `saved` doesn't run yet
+    pub(crate) fn new_scripts<'a>(&'a self, saved: &SourceSettings) -> Vec<&'a ScriptSourceConfig> {
+        self.scripts.iter()
+            .filter(|script| !saved.scripts.iter().any(|s| s.command == script.command && s.args == script.args))
+            .collect()
+    }
+}
+
+/// Asks the user whether a script source may run, in a native dialog.
+/// The webview can't answer it, so a compromised page can't make discovery run commands of its choosing.
+pub(crate) async fn confirm_script(app: &AppHandle, script: &ScriptSourceConfig) -> bool {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(format!(
+            "Kuboid will run this command whenever it looks for contexts, and read kubeconfigs from its output:\n\n{}",
+            script.command_line()
+        ))
+        .title(format!("Run kubeconfig script \"{}\"?", script.name))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom("Run".to_string(), "Don't run".to_string()))
+        .show(move |confirmed| {
+            let _ = tx.send(confirmed);
+        });
+    rx.await.unwrap_or(false)
+}
+
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type", content = "info")]
+pub(crate) enum KubeConfigOrigin {
+    EnvironmentOrDefaultPath,
+    Script {
+        name: String,
+        command: String,
+    },
+    File {
+        path: PathBuf,
+    },
+    /// Only used to report a directory that couldn't be scanned,
+    /// the files inside a directory are reported as [KubeConfigOrigin::File]
+    Directory {
+        path: PathBuf,
+    },
+}
+
+/// Why a kubeconfig source could not be loaded
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type", content = "info")]
+pub(crate) enum SourceError {
+    /// The script could not be started at all (e.g. command not found)
+    Spawn {
+        message: String,
+    },
+    /// The script did not finish within its configured timeout
+    Timeout {
+        timeout_secs: u64,
+    },
+    /// The script exited unsuccessfully
+    Exit {
+        code: Option<i32>,
+        stderr: String,
+    },
+    /// The file or directory could not be read
+    Read {
+        message: String,
+    },
+    /// The source produced output that isn't a valid kubeconfig
+    Parse {
+        message: String,
+    },
+    /// The kubeconfig is valid but cannot be merged with the earlier sources
+    Merge {
+        message: String,
+    },
+}
+
+#[derive(Serialize,Clone,Copy,Debug,PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ConflictKind {
+    Context,
+    Cluster,
+    User,
+}
+
+/// An entry that was ignored because an earlier source already defined the same name
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MergeConflict {
+    pub(crate) kind: ConflictKind,
+    pub(crate) name: String,
+}
+
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ContextSourceInfo {
+    pub(crate) contexts: Vec<String>,
+    pub(crate) origin: KubeConfigOrigin,
+    pub(crate) error: Option<SourceError>,
+    pub(crate) conflicts: Vec<MergeConflict>,
+    /// Filled in by [crate::flavour::describe_contexts]
+    pub(crate) details: Vec<ContextDetails>,
+}
+
+#[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KubeConfigInfo {
+    pub(crate) merged: Option<Kubeconfig>,
+    pub(crate) sources: Vec<ContextSourceInfo>
+}
+
+impl KubeConfigInfo {
+    fn add_source(&mut self, origin: KubeConfigOrigin, result: Result<Kubeconfig, SourceError>) {
+        let config = match result {
+            Ok(config) => config,
+            Err(error) => {
+                self.push_error(origin, error);
+                return;
+            }
+        };
+
+        let context_names: Vec<String> = config.contexts.iter().map(|c| c.name.clone()).collect();
+        let (merged, conflicts) = match self.merged.take() {
+            Some(existing) => {
+                let conflicts = find_conflicts(&existing, &config);
+                match existing.clone().merge(config) {
+                    Ok(new_merged) => (new_merged, conflicts),
+                    Err(e) => {
+                        // keep the existing config, and tell the user why this source is missing
+                        self.merged = Some(existing);
+                        self.push_error(origin, SourceError::Merge { message: e.to_string() });
+                        return;
+                    }
+                }
+            },
+            None => (config, Vec::new()),
+        };
+        for conflict in &conflicts {
+            eprintln!("Kubeconfig source {:?} redefines {:?} {}, keeping the earlier definition", origin, conflict.kind, conflict.name);
+        }
+
+        self.merged = Some(merged);
+        self.sources.push(ContextSourceInfo {
+            contexts: context_names,
+            origin,
+            error: None,
+            conflicts,
+            details: Vec::new(),
+        });
+    }
+
+    fn push_error(&mut self, origin: KubeConfigOrigin, error: SourceError) {
+        eprintln!("Kubeconfig source {:?} failed: {:?}", origin, error);
+        self.sources.push(ContextSourceInfo {
+            contexts: Vec::new(),
+            origin,
+            error: Some(error),
+            conflicts: Vec::new(),
+            details: Vec::new(),
+        });
+    }
+}
+
+/// Finds the names in `next` that `existing` already defines.
+/// Kubeconfig merging keeps the first definition, so these entries from `next` are dropped.
+fn find_conflicts(existing: &Kubeconfig, next: &Kubeconfig) -> Vec<MergeConflict> {
+    let mut conflicts = Vec::new();
+    for context in &next.contexts {
+        if existing.contexts.iter().any(|c| c.name == context.name) {
+            conflicts.push(MergeConflict { kind: ConflictKind::Context, name: context.name.clone() });
+        }
+    }
+    for cluster in &next.clusters {
+        if existing.clusters.iter().any(|c| c.name == cluster.name) {
+            conflicts.push(MergeConflict { kind: ConflictKind::Cluster, name: cluster.name.clone() });
+        }
+    }
+    for user in &next.auth_infos {
+        if existing.auth_infos.iter().any(|u| u.name == user.name) {
+            conflicts.push(MergeConflict { kind: ConflictKind::User, name: user.name.clone() });
+        }
+    }
+    conflicts
+}
+
+fn file_source_error(e: KubeconfigError) -> SourceError {
+    match e {
+        KubeconfigError::ReadConfig(..) | KubeconfigError::FindPath => SourceError::Read { message: e.to_string() },
+        _ => SourceError::Parse { message: e.to_string() },
+    }
+}
+
+/// Reads a single kubeconfig file, resolving relative certificate paths against its directory
+fn read_file_source(path: &Path) -> Result<Kubeconfig, SourceError> {
+    Kubeconfig::read_from(path).map_err(file_source_error)
+}
+
+/// Lists the kubeconfig files in a directory, sorted by name so the merge order is stable.
+/// Hidden files and subdirectories are skipped.
+fn list_directory_source(path: &Path) -> Result<Vec<PathBuf>, SourceError> {
+    let entries = std::fs::read_dir(path).map_err(|e| SourceError::Read { message: e.to_string() })?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Command and arguments
+type ScriptKey = (String, Vec<String>);
+
+/// Kubeconfigs printed by script sources, so listing contexts and kubeconfig changes don't run every script every time.
+/// Failures aren't kept, a fixed script is picked up by the next discovery.
+#[derive(Default)]
+pub(crate) struct ScriptOutputs(Mutex<HashMap<ScriptKey, (Kubeconfig, Instant)>>);
+
+impl ScriptOutputs {
+    fn get(&self, key: &ScriptKey) -> Option<Kubeconfig> {
+        let outputs = self.0.lock().ok()?;
+        let (config, ran) = outputs.get(key)?;
+        (ran.elapsed() < SCRIPT_OUTPUT_TTL).then(|| config.clone())
+    }
+
+    fn insert(&self, key: ScriptKey, config: Kubeconfig) {
+        if let Ok(mut outputs) = self.0.lock() {
+            outputs.retain(|_, (_, ran)| ran.elapsed() < SCRIPT_OUTPUT_TTL);
+            outputs.insert(key, (config, Instant::now()));
+        }
+    }
+
+    /// Forgets every output, for when the user asks for fresh credentials
+    pub(crate) fn clear(&self) {
+        if let Ok(mut outputs) = self.0.lock() {
+            outputs.clear();
+        }
+    }
+}
+
+/// The kubeconfig of a script source, from `outputs` if it ran recently
+async fn script_source(script: &ScriptSourceConfig, outputs: &ScriptOutputs) -> Result<Kubeconfig, SourceError> {
+    let key = (script.command.clone(), script.args.clone());
+    if let Some(config) = outputs.get(&key) {
+        return Ok(config);
+    }
+    let config = run_script_source(script).await?;
+    outputs.insert(key, config.clone());
+    Ok(config)
+}
+
+/// Runs a script source and parses its stdout as a kubeconfig
+async fn run_script_source(script: &ScriptSourceConfig) -> Result<Kubeconfig, SourceError> {
+    let child = tokio::process::Command::new(&script.command)
+        .args(&script.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| SourceError::Spawn { message: e.to_string() })?;
+
+    let output = tokio::time::timeout(Duration::from_secs(script.timeout_secs), child.wait_with_output())
+        .await
+        .map_err(|_| SourceError::Timeout { timeout_secs: script.timeout_secs })?
+        .map_err(|e| SourceError::Spawn { message: e.to_string() })?;
+
+    if !output.status.success() {
+        return Err(SourceError::Exit {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Kubeconfig::from_yaml(&stdout).map_err(|e| SourceError::Parse { message: e.to_string() })
+}
+
+/// Reads every configured kubeconfig source and merges them together.
+/// Scripts run at the same time, unless they ran recently (see [ScriptOutputs]).
+pub(crate) async fn discover(settings: &SourceSettings, scripts: &ScriptOutputs) -> KubeConfigInfo {
+    let mut kci = KubeConfigInfo {
+        merged: None,
+        sources: Vec::new()
+    };
+
+    // Reads from KUBECONFIG env var or default location (~/.kube/config)
+    // A missing default kubeconfig is reported as an error on this source,
+    // so the welcome screen can explain how to add one
+    kci.add_source(KubeConfigOrigin::EnvironmentOrDefaultPath, Kubeconfig::read().map_err(file_source_error));
+
+    let outputs = futures_util::future::join_all(settings.scripts.iter().map(|script| script_source(script, scripts))).await;
+    // Merged in the configured order, whichever finished first
+    for (script, output) in settings.scripts.iter().zip(outputs) {
+        kci.add_source(KubeConfigOrigin::Script {
+            name: script.name.clone(),
+            command: script.command.clone(),
+        }, output);
+    }
+
+    for path in &settings.files {
+        kci.add_source(KubeConfigOrigin::File { path: path.clone() }, read_file_source(path));
+    }
+
+    for dir in &settings.directories {
+        match list_directory_source(dir) {
+            Ok(files) => {
+                for path in files {
+                    let result = read_file_source(&path);
+                    kci.add_source(KubeConfigOrigin::File { path }, result);
+                }
+            }
+            Err(error) => kci.push_error(KubeConfigOrigin::Directory { path: dir.clone() }, error),
+        }
+    }
+
+    kci
+}
+
+/// Files and directories whose changes should trigger a new discovery
+pub(crate) fn watched_paths(app: &AppHandle, settings: &SourceSettings) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    match std::env::var_os("KUBECONFIG") {
+        Some(value) => paths.extend(std::env::split_paths(&value).filter(|p| !p.as_os_str().is_empty())),
+        None => {
+            if let Ok(home) = app.path().home_dir() {
+                paths.push(home.join(".kube").join("config"));
+            }
+        }
+    }
+    paths.extend(settings.files.iter().cloned());
+    paths.extend(settings.directories.iter().cloned());
+    paths
+}
+
+/// Watches kubeconfig files and directories, sending a message on `tx` whenever one of them changes.
+///
+/// Files are watched through their parent directory, because most tools (including kubectl)
+/// rewrite kubeconfigs by replacing the file instead of writing to it in place.
+pub(crate) fn watch_paths(
+    paths: Vec<PathBuf>,
+    tx: tokio::sync::mpsc::UnboundedSender<()>,
+) -> notify::Result<RecommendedWatcher> {
+    let targets = paths.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if event.kind.is_access() {
+            return;
+        }
+        let relevant = event.paths.iter().any(|changed| {
+            targets.iter().any(|target| changed == target || changed.parent() == Some(target.as_path()))
+        });
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+
+    for path in &paths {
+        let dir = if path.is_dir() {
+            path.as_path()
+        } else {
+            match path.parent() {
+                Some(parent) => parent,
+                None => continue,
+            }
+        };
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            eprintln!("Failed to watch {} for kubeconfig changes: {}", dir.display(), e);
+        }
+    }
+    Ok(watcher)
+}
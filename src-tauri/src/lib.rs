@@ -1,23 +1,52 @@
 use futures_util::StreamExt;
 use futures_util::TryStreamExt;
-use kube::api::DynamicObject;
+use kube::api::{DynamicObject, ListParams, WatchEvent, WatchParams};
 use kube::config::{AuthInfo, KubeConfigOptions, Kubeconfig};
 use kube::discovery::ApiGroup;
-use kube::runtime::{watcher, WatchStreamExt};
+use kube::core::PartialObjectMeta;
+use kube::runtime::{metadata_watcher, watcher};
+use kube::runtime::utils::Backoff;
 use kube::runtime::watcher::{watch_object, Event, InitialListStrategy, ListSemantic};
 use kube::{Api, Client, Config, Discovery, Resource};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::fs::exists;
 use std::process::Command;
+use std::time::{Duration, Instant};
 use std::sync::MutexGuard;
 use futures_util::stream::BoxStream;
 use tauri::async_runtime::{Mutex, TokioJoinHandle};
 use tauri::http::{Request, Uri};
 use tauri::ipc::Channel;
-use tauri::{async_runtime, Manager, State};
+use tauri::webview::PageLoadEvent;
+use tauri::{async_runtime, AppHandle, Manager, State};
+use kubeconfig::{KubeConfigInfo, ScriptOutputs, SourceSettings};
+use error::CommandError;
+use flavour::ServerVersions;
+use health::{HealthReporter, WatchState};
+use list_strategy::{ClusterListStrategy, ListSettings, ListStrategy};
+use object_cache::{ObjectCache, SharedObject};
+use proxy::{ProxySettings, ProxySettingsInfo};
+use probe::ProbeResult;
+use query::{CompiledQuery, ListQuery, QueryWindow};
+use subset::{SubsetFilter, SubsetStream};
 use debug_ignore::DebugIgnore;
+use watch_cache::{PersistedList, WatchCacheStore};
+
+mod error;
+mod flavour;
+mod health;
+mod kubeconfig;
+mod list_strategy;
+mod object_cache;
+mod probe;
+mod proxy;
+mod query;
+mod settings;
+mod subset;
+mod table;
+mod watch_cache;
 
 
 impl Display for ResourceListenEvent {
@@ -28,8 +57,15 @@ impl Display for ResourceListenEvent {
             ResourceListenEvent::InitApply { .. } => write!(f, "InitApply"),
             ResourceListenEvent::Apply { .. } => write!(f, "Apply"),
             ResourceListenEvent::Delete { .. } => write!(f, "Delete"),
+            ResourceListenEvent::Patch { uid, .. } => write!(f, "Patch {}", uid),
+            ResourceListenEvent::Batch { events } => write!(f, "Batch of {}", events.len()),
+            ResourceListenEvent::TableColumns { .. } => write!(f, "TableColumns"),
+            ResourceListenEvent::State { state } => write!(f, "State {:?}", state),
+            ResourceListenEvent::Stale => write!(f, "Stale"),
+            ResourceListenEvent::Live => write!(f, "Live"),
             ResourceListenEvent::Error { message } => write!(f, "Error: {}", message),
             ResourceListenEvent::SingleResourceNotFoundOrDeleted => write!(f, "SingleResourceNotFoundOrDeleted"),
+            ResourceListenEvent::FromContext { context, event } => write!(f, "{} (from {})", event, context),
         }
     }
 }
@@ -39,18 +75,45 @@ enum ResourceListenEvent {
     Init,
     InitDone,
     InitApply {
-        resource: serde_json::Value
+        resource: SharedObject
     },
     Apply {
-        resource: serde_json::Value
+        resource: SharedObject
     },
     Delete {
-        resource: serde_json::Value
+        resource: SharedObject
+    },
+    /// An update to a resource the client already has, as RFC 6902 operations
+    /// against the previous version
+    Patch {
+        uid: String,
+        ops: json_patch::Patch
     },
     Error {
         message: String
     },
     SingleResourceNotFoundOrDeleted,
+    /// An event from one of the contexts of a composed subscription
+    FromContext {
+        context: String,
+        event: Box<ResourceListenEvent>
+    },
+    /// Several events sent at once, in order (see [BatchConfig])
+    Batch {
+        events: Vec<ResourceListenEvent>
+    },
+    /// The watcher's health changed
+    State {
+        state: WatchState
+    },
+    /// Column definitions of a Table subscription, sent before its rows
+    TableColumns {
+        columns: serde_json::Value
+    },
+    /// The list just sent comes from the on-disk cache and may be out of date
+    Stale,
+    /// The stale list has caught up with the cluster
+    Live,
 }
 
 impl From<Event<DynamicObject>> for ResourceListenEvent {
@@ -59,464 +122,1775 @@ impl From<Event<DynamicObject>> for ResourceListenEvent {
             Event::Init => ResourceListenEvent::Init,
             Event::InitDone => ResourceListenEvent::InitDone,
             Event::InitApply(e) => ResourceListenEvent::InitApply {
-                resource: serde_json::to_value(e).unwrap(),
+                resource: Arc::new(serde_json::to_value(e).unwrap()),
             },
             Event::Apply(e) =>
                 ResourceListenEvent::Apply {
-                    resource: serde_json::to_value(e).unwrap(),
+                    resource: Arc::new(serde_json::to_value(e).unwrap()),
                 },
             Event::Delete(e) =>
                 ResourceListenEvent::Delete {
-                    resource: serde_json::to_value(e).unwrap(),
+                    resource: Arc::new(serde_json::to_value(e).unwrap()),
                 },
         }
     }
 }
 
-#[derive(Serialize,Clone,Debug)]
-#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type", content = "info")]
-enum KubeConfigOrigin {
-    EnvironmentOrDefaultPath
+/// Called by the client on startup to discover available kube contexts.
+/// With `refresh`, script sources run again even if they ran recently.
+#[tauri::command]
+async fn list_kube_contexts(app: AppHandle, ctx: CommandGlobalState<'_>, refresh: Option<bool>) -> Result<KubeConfigInfo, ()> {
+    if refresh.unwrap_or(false) {
+        ctx.script_outputs.clear();
+    }
+    let kci = discover_kube_contexts(&app).await;
+
+    *ctx.kubeconfig.write().await = kci.merged.clone();
+    Ok(kci)
+}
+
+/// Reads every kubeconfig source and works out what kind of cluster each context points at
+async fn discover_kube_contexts(app: &AppHandle) -> KubeConfigInfo {
+    let settings = SourceSettings::load(app);
+    let state = app.state::<GlobalState>();
+    let mut kci = kubeconfig::discover(&settings, &state.script_outputs).await;
+
+    // A failing source may only be down for now, so its contexts' lists are kept until it loads again
+    if let (Some(store), true) = (&state.watch_cache, kci.sources.iter().all(|s| s.error.is_none())) {
+        let contexts: HashSet<String> = kci.sources.iter().flat_map(|s| s.contexts.iter().cloned()).collect();
+        let store = store.clone();
+        tokio::task::spawn_blocking(move || store.prune(Some(&contexts)));
+    }
+
+    let proxy_settings = state.proxy_settings.read().await.clone();
+    flavour::describe_contexts(&mut kci, &proxy_settings, &state.server_versions).await;
+    kci
+}
+
+/// Returns the user-configured kubeconfig sources (scripts, etc.)
+#[tauri::command]
+async fn get_kubeconfig_sources(app: AppHandle) -> Result<SourceSettings, ()> {
+    Ok(SourceSettings::load(&app))
 }
 
+/// Replaces the user-configured kubeconfig sources.
+/// Scripts with commands that weren't configured before only run once the user confirms them in a native dialog.
+/// The client should call list_kube_contexts again afterwards.
+#[tauri::command]
+async fn set_kubeconfig_sources(app: AppHandle, ctx: CommandGlobalState<'_>, settings: SourceSettings) -> Result<(), CommandError> {
+    let saved = SourceSettings::load(&app);
+    for script in settings.new_scripts(&saved) {
+        if !kubeconfig::confirm_script(&app, script).await {
+            return Err(CommandError::ScriptNotConfirmed { command: script.command_line() });
+        }
+    }
+    settings.save(&app)?;
+
+    // The watched files may have changed, so re-arm the watcher if one is running
+    let mut kubeconfig_watch = ctx.kubeconfig_watch.lock().await;
+    if let Some(watch) = kubeconfig_watch.take() {
+        TokioJoinHandle::abort(&watch.task);
+        *kubeconfig_watch = Some(start_kubeconfig_watch(&app, watch.channel).map_err(|e| e.to_string())?);
+    }
+    Ok(())
+}
+
+/// Returns the per-context proxy overrides, without their passwords
+#[tauri::command]
+async fn get_proxy_settings(ctx: CommandGlobalState<'_>) -> Result<ProxySettingsInfo, ()> {
+    Ok(ctx.proxy_settings.read().await.info())
+}
+
+/// Replaces the per-context proxy overrides. Unset passwords keep the saved ones.
+/// Contexts that are already connected keep their proxy until they are reconnected.
+#[tauri::command]
+async fn set_proxy_settings(app: AppHandle, ctx: CommandGlobalState<'_>, settings: ProxySettings) -> Result<(), CommandError> {
+    let mut saved = ctx.proxy_settings.write().await;
+    let settings = settings.merge_saved(&saved)?;
+    settings.save(&app)?;
+    *saved = settings;
+    // Contexts are reached through other proxies now, so whether they answer may have changed
+    ctx.server_versions.clear();
+    Ok(())
+}
+
+/// Returns how watchers list when a cluster doesn't support streaming lists, and how long unused ones are kept
+#[tauri::command]
+async fn get_list_settings(ctx: CommandGlobalState<'_>) -> Result<ListSettings, ()> {
+    Ok(ctx.list_settings.read().await.clone())
+}
+
+/// Replaces the list settings. The page size applies to watchers started afterwards,
+/// the linger period and idle memory budget right away.
+#[tauri::command]
+async fn set_list_settings(app: AppHandle, ctx: CommandGlobalState<'_>, settings: ListSettings) -> Result<(), CommandError> {
+    settings.save(&app)?;
+    *ctx.list_settings.write().await = settings;
+    evict_idle_watchers(&ctx).await;
+    Ok(())
+}
 
 #[derive(Serialize,Clone,Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "event", content = "data")]
+enum KubeContextsEvent {
+    /// A kubeconfig source changed on disk and discovery was re-run
+    ContextsChanged {
+        info: KubeConfigInfo
+    },
+    /// The credentials of a connected context changed, and a new client was built for it.
+    /// Its watchers relist with the new client, so subscriptions carry on by themselves
+    ClientRebuilt {
+        context_name: String
+    },
+    ClientRebuildFailed {
+        context_name: String,
+        message: String
+    },
+}
+
+struct KubeconfigWatch {
+    // Dropping the watcher stops the notifications
+    _watcher: notify::RecommendedWatcher,
+    task: TokioJoinHandle<()>,
+    channel: Channel<KubeContextsEvent>,
+}
+
+/// Watches every kubeconfig source on disk and pushes the re-discovered contexts to the client
+/// whenever one of them changes.
+/// Only one watch is active at a time, calling this again replaces the previous channel.
+#[tauri::command]
+async fn watch_kube_contexts(
+    app: AppHandle,
+    ctx: CommandGlobalState<'_>,
+    channel: Channel<KubeContextsEvent>
+) -> Result<(), CommandError> {
+    let mut kubeconfig_watch = ctx.kubeconfig_watch.lock().await;
+    if let Some(watch) = kubeconfig_watch.take() {
+        TokioJoinHandle::abort(&watch.task);
+    }
+    *kubeconfig_watch = Some(start_kubeconfig_watch(&app, channel).map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn start_kubeconfig_watch(app: &AppHandle, channel: Channel<KubeContextsEvent>) -> notify::Result<KubeconfigWatch> {
+    let settings = SourceSettings::load(app);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = kubeconfig::watch_paths(kubeconfig::watched_paths(app, &settings), tx)?;
+
+    let app = app.clone();
+    let task_channel = channel.clone();
+    let task = tokio::task::spawn(async move {
+        while rx.recv().await.is_some() {
+            // Tools usually write several events per update, wait for them to settle
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            while rx.try_recv().is_ok() {}
+
+            refresh_kube_contexts(&app, &task_channel).await;
+        }
+    });
+
+    Ok(KubeconfigWatch {
+        _watcher: watcher,
+        task,
+        channel,
+    })
+}
+
+/// Re-runs discovery after a kubeconfig change,
+/// and rebuilds the client of every connected context whose credentials changed, restarting its watchers with it
+async fn refresh_kube_contexts(app: &AppHandle, channel: &Channel<KubeContextsEvent>) {
+    let kci = discover_kube_contexts(app).await;
+
+    let state = app.state::<GlobalState>();
+
+    let previous = std::mem::replace(&mut *state.kubeconfig.write().await, kci.merged.clone());
+    let _ = channel.send(KubeContextsEvent::ContextsChanged { info: kci });
+
+    let Some(kubeconfig) = state.kubeconfig.read().await.clone() else { return };
+    let proxy_settings = state.proxy_settings.read().await.clone();
+    let list_settings = state.list_settings.read().await.clone();
+    let connections: Vec<(String, Arc<ClusterConnection>)> = state.connections.read().await.iter()
+        .map(|(context_name, connection)| (context_name.clone(), connection.clone()))
+        .collect();
+    for (context_name, connection) in connections {
+        if previous.as_ref().map(|p| context_fingerprint(p, &context_name)) == Some(context_fingerprint(&kubeconfig, &context_name)) {
+            continue;
+        }
+
+        eprintln!("Credentials for context {} changed, rebuilding client", context_name);
+        match build_client(kubeconfig.clone(), &context_name, &proxy_settings).await {
+            Ok(mut rebuilt) => {
+                // The API server is still the same
+                rebuilt.list_strategy = connection.list_strategy.clone();
+                let rebuilt = Arc::new(rebuilt);
+                let mut connections = state.connections.write().await;
+                if let Some(connection) = connections.get_mut(&context_name) {
+                    *connection = rebuilt.clone();
+                    // Running watchers still hold the old client, they list again with the new one
+                    if let Some(context_watchers) = state.watchers.lock().await.get_mut(&context_name) {
+                        for (key, shared) in context_watchers.watchers.iter_mut() {
+                            shared.restart(&rebuilt, key, &list_settings);
+                        }
+                    }
+                }
+                drop(connections);
+                let _ = channel.send(KubeContextsEvent::ClientRebuilt { context_name });
+            }
+            Err(e) => {
+                let _ = channel.send(KubeContextsEvent::ClientRebuildFailed { context_name, message: e.to_string() });
+            }
+        }
+    }
+}
+
+/// Everything in a kubeconfig that the client for `context_name` is built from
+fn context_fingerprint(kubeconfig: &Kubeconfig, context_name: &str) -> serde_json::Value {
+    let context = kubeconfig.contexts.iter().find(|c| c.name == context_name);
+    let inner = context.and_then(|c| c.context.as_ref());
+    let cluster = inner.and_then(|c| kubeconfig.clusters.iter().find(|cl| cl.name == c.cluster));
+    let user = inner
+        .and_then(|c| c.user.as_ref())
+        .and_then(|u| kubeconfig.auth_infos.iter().find(|a| &a.name == u));
+    serde_json::json!({
+        "context": context,
+        "cluster": cluster,
+        "user": user,
+    })
+}
+
+async fn build_config(kubeconfig: Kubeconfig, context_name: &str, proxy_settings: &ProxySettings) -> Result<Config, CommandError> {
+    let mut config = Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions {
+        context: Some(context_name.to_string()),
+        cluster: None,
+        user: None,
+    }).await?;
+    proxy_settings.apply(context_name, &mut config)?;
+    Ok(config)
+}
+
+async fn build_client(kubeconfig: Kubeconfig, context_name: &str, proxy_settings: &ProxySettings) -> Result<ClusterConnection, CommandError> {
+    let config = build_config(kubeconfig, context_name, proxy_settings).await?;
+
+    let proxy_url = config.proxy_url.as_ref().map(proxy::display_proxy_url);
+    let client = proxy::client(config).map_err(|e| match (&e, &proxy_url) {
+        (kube::Error::ProxyProtocolUnsupported { .. } | kube::Error::ProxyProtocolDisabled { .. }, Some(proxy_url)) => {
+            CommandError::Proxy { proxy_url: proxy_url.clone(), message: e.to_string() }
+        }
+        _ => CommandError::ClientConstruction { message: e.to_string() },
+    })?;
+    Ok(ClusterConnection::new(client, proxy_url))
+}
+
+/// Connects to the client's desired context
+/// (usually selected from the list provided by list_kube_contexts)
+/// and makes it the default for commands that don't specify a context.
+/// Connections to other contexts stay open.
+#[tauri::command]
+async fn start(ctx: CommandGlobalState<'_>, context_name: String) -> Result<(), CommandError> {
+    ctx.connect(&context_name).await?;
+    *ctx.current_context.write().await = Some(context_name);
+    Ok(())
+}
+
+/// Checks whether a context is usable without connecting to it:
+/// latency, server version, readiness, who we're authenticated as, or why it failed.
+#[tauri::command]
+async fn probe_context(ctx: CommandGlobalState<'_>, context_name: String) -> Result<ProbeResult, CommandError> {
+    let kubeconfig = ctx.kubeconfig.read().await.clone().ok_or(CommandError::KubeconfigNotLoaded)?;
+    let proxy_settings = ctx.proxy_settings.read().await.clone();
+
+    let config = build_config(kubeconfig, &context_name, &proxy_settings).await?;
+    Ok(probe::probe(config).await)
+}
+
+/// Closes the connection to a context, stopping every subscription on it
+#[tauri::command]
+async fn disconnect(ctx: CommandGlobalState<'_>, context_name: String) -> Result<(), CommandError> {
+    // Together, so that subscriptions can't attach to the context once it's gone
+    let removed = {
+        let mut connections = ctx.connections.write().await;
+        connections.remove(&context_name);
+        ctx.watchers.lock().await.remove(&context_name)
+    };
+
+    // Composed subscriptions lose the context and keep the others, the rest stop
+    let mut task_ids = Vec::new();
+    for task in ctx.task_map.lock().await.values_mut().filter(|t| t.metadata.contexts.contains(&context_name)) {
+        match task.context_bridges.remove(&context_name) {
+            Some(bridge) if task.metadata.contexts.len() > 1 => {
+                let _ = bridge.stop.send(());
+                task.metadata.contexts.retain(|c| c != &context_name);
+                task.sources.retain(|s| s.context != context_name);
+            }
+            _ => task_ids.push(task.metadata.id),
+        }
+    }
+    for task_id in task_ids {
+        release_task(&ctx, task_id).await;
+    }
+
+    if let Some(watchers) = removed {
+        for (key, shared) in &watchers.watchers {
+            TokioJoinHandle::abort(&shared.source_task);
+            persist_watcher(ctx.watch_cache.as_ref(), &context_name, key, shared);
+        }
+    }
+    let mut current_context = ctx.current_context.write().await;
+    if current_context.as_ref() == Some(&context_name) {
+        *current_context = None;
+    }
+    Ok(())
+}
+
+/// Retrieves detailed information about a specific Kubernetes resource.
+#[tauri::command]
+async fn detail_resource(
+    state: CommandGlobalState<'_>,
+    group: String,
+    api_version: String,
+    resource_plural: String,
+    name: String,
+    namespace: Option<String>,
+    context: Option<String>,
+) -> Result<serde_json::Value, CommandError> {
+    let connection = state.connection(context).await?;
+    let (client, proxy_url) = (connection.client.clone(), connection.proxy_url.clone());
+
+    let ar = kube::discovery::ApiResource {
+        group,
+        api_version,
+        plural: resource_plural,
+        version: "".to_string(),
+        kind: "".to_string(),
+    };
+    let api: Api<DynamicObject> = match namespace {
+        Some(ns) => Api::namespaced_with(client, &ns, &ar),
+        None => Api::all_with(client, &ar),
+    };
+
+    let obj = api.get(&name).await.map_err(|e| proxy::request_error(proxy_url.as_ref(), e))?;
+    Ok(serde_json::to_value(obj).map_err(|e| e.to_string())?)
+}
+
+/// Stops a bridge task and releases its reference on the shared watcher of every context it reads from.
+/// Returns false if there is no such task.
+async fn release_task(state: &GlobalState, task_id: i32) -> bool {
+    // 1. Remove Bridge Task
+    let Some(task_handle) = state.task_map.lock().await.remove(&task_id) else {
+        eprintln!("[{}] No task found to abort", task_id);
+        return false;
+    };
+    eprintln!("[{}] Stopping bridge task", task_id);
+    TokioJoinHandle::abort(&task_handle.handle);
+    for bridge in task_handle.context_bridges.values() {
+        bridge.handle.abort();
+    }
+
+    // 2. Decrement Ref Count on Shared Watcher
+    let mut watchers = state.watchers.lock().await;
+    for WatcherSource { context, key, .. } in &task_handle.sources {
+        let Some(context_watchers) = watchers.get_mut(context) else { continue };
+        if let Some(shared) = context_watchers.watchers.get_mut(key) {
+            shared.ref_count -= 1;
+            eprintln!("[{}] Decremented ref count for {:?} on {} to {}", task_id, key, context, shared.ref_count);
+            if shared.ref_count == 0 {
+                // Keep watching for a while, the client often comes back to the list (see [evict_idle_watchers])
+                eprintln!("[{}] Source task for {:?} on {} is unused", task_id, key, context);
+                shared.idle_since = Some(Instant::now());
+            }
+        }
+    }
+    drop(watchers);
+    evict_idle_watchers(state).await;
+    true
+}
+
+/// Stops a shared watcher's source task and forgets the watcher
+fn stop_watcher(context_watchers: &mut ContextWatchers, context: &str, key: &SubscriptionKey, store: Option<&WatchCacheStore>) {
+    let Some(shared) = context_watchers.watchers.remove(key) else { return };
+    TokioJoinHandle::abort(&shared.source_task);
+    // Remember how big the list was, to size the next watcher's broadcast channel
+    let list_size = shared.cache.read().map(|c| c.len()).unwrap_or_default();
+    if context_watchers.list_sizes.len() >= MAX_LIST_SIZES && !context_watchers.list_sizes.contains_key(key) {
+        let oldest = context_watchers.list_sizes.iter().min_by_key(|(_, (_, at))| *at).map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            context_watchers.list_sizes.remove(&oldest);
+        }
+    }
+    context_watchers.list_sizes.insert(key.clone(), (list_size, Instant::now()));
+    persist_watcher(store, context, key, &shared);
+}
+
+/// Stops the unused watchers that lingered long enough, and the least recently used ones
+/// until the rest fit in the idle memory budget
+async fn evict_idle_watchers(state: &GlobalState) {
+    let settings = state.list_settings.read().await.clone();
+    let linger = settings.linger();
+    let budget = settings.idle_budget_bytes();
+    let mut watchers = state.watchers.lock().await;
+    let mut idle: Vec<(Instant, usize, String, SubscriptionKey)> = watchers.iter()
+        .flat_map(|(context, context_watchers)| context_watchers.watchers.iter().filter_map(move |(key, shared)| {
+            let since = shared.idle_since?;
+            let bytes = shared.cache.read().map(|c| c.bytes()).unwrap_or_default();
+            Some((since, bytes, context.clone(), key.clone()))
+        }))
+        .collect();
+    // Most recently used first, they are the last to go
+    idle.sort_by_key(|(since, ..)| std::cmp::Reverse(*since));
+
+    let mut kept_bytes = 0;
+    for (since, bytes, context, key) in idle {
+        let reason = if since.elapsed() >= linger {
+            "lingered"
+        } else if kept_bytes + bytes > budget {
+            "over the idle memory budget"
+        } else {
+            kept_bytes += bytes;
+            continue;
+        };
+        eprintln!("Stopping unused source task for {:?} on {} ({})", key, context, reason);
+        if let Some(context_watchers) = watchers.get_mut(&context) {
+            stop_watcher(context_watchers, &context, &key, state.watch_cache.as_ref());
+        }
+    }
+}
+
+/// How often unused watchers are checked for having lingered long enough
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+async fn reap_idle_watchers(app: AppHandle) {
+    let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        evict_idle_watchers(&app.state::<GlobalState>()).await;
+    }
+}
+
+/// Stops a running subscription by aborting the task associated with it.
+#[tauri::command]
+async fn stop_listen_task(
+    state: CommandGlobalState<'_>,
+    task_id: i32
+) -> Result<(), CommandError> {
+    if release_task(&state, task_id).await {
+        Ok(())
+    } else {
+        Err(CommandError::SubscriptionNotFound { id: task_id })
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct ContextSourceInfo {
+struct TaskMetadata {
+    id: i32,
+    /// Contexts this task reads from, more than one for composed subscriptions
     contexts: Vec<String>,
-    origin: KubeConfigOrigin
+    group: String,
+    api_version: String,
+    resource_plural: String,
+    name: Option<String>,
+    namespace: Option<String>,
+    namespaces: Option<Vec<String>>,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    metadata_only: bool,
+    as_table: bool,
 }
 
-#[derive(Serialize,Clone,Debug)]
-#[serde(rename_all = "camelCase")]
-struct KubeConfigInfo {
-    merged: Option<Kubeconfig>,
-    sources: Vec<ContextSourceInfo>
+/// A shared watcher a subscription reads from
+#[derive(Clone)]
+struct WatcherSource {
+    context: String,
+    /// The watcher's key, broader than the subscription's when it's served from a superset
+    key: SubscriptionKey,
+    /// The subscription's part of the watcher's list, if it's served from a superset
+    filter: Option<Arc<SubsetFilter>>,
+}
+
+struct TaskHandle {
+    handle: TokioJoinHandle<()>,
+    metadata: TaskMetadata,
+    /// One per context
+    sources: Vec<WatcherSource>,
+    /// Set for window subscriptions, to change the window they push
+    query: Option<tokio::sync::watch::Sender<CompiledQuery>>,
+    /// Set for event subscriptions, to send the list again (see `resync_subscription`)
+    resync: Option<tokio::sync::watch::Sender<()>>,
+    /// The bridge of each context of a composed subscription, so a context can go without the others
+    context_bridges: HashMap<String, ContextBridge>,
 }
 
+/// One context's bridge in a composed subscription (see [run_context_bridge])
+struct ContextBridge {
+    /// Tells the bridge its context was disconnected
+    stop: tokio::sync::oneshot::Sender<()>,
+    handle: tokio::task::AbortHandle,
+}
 
-/// Called by the client on startup to discover available kube contexts
-#[tauri::command]
-async fn list_kube_contexts(ctx: CommandGlobalState<'_>) -> Result<KubeConfigInfo, ()> {
-    let mut kci = KubeConfigInfo {
-        merged: None,
-        sources: Vec::new()
+const MIN_BROADCAST_CAPACITY: usize = 100;
+const MAX_BROADCAST_CAPACITY: usize = 16384;
+
+/// How many stopped watchers' list sizes a context remembers
+const MAX_LIST_SIZES: usize = 256;
+
+/// Broadcast capacity for a list of `list_size` objects.
+/// A relist sends every object again, so leave room for twice the list.
+fn broadcast_capacity(list_size: Option<usize>) -> usize {
+    list_size
+        .map(|size| size.saturating_mul(2))
+        .unwrap_or_default()
+        .clamp(MIN_BROADCAST_CAPACITY, MAX_BROADCAST_CAPACITY)
+}
+
+/// Best guess at how many objects `key` lists, before listing it:
+/// its size when it was last watched or persisted, else the biggest list of the same resource type on the context
+fn known_list_size(context_watchers: &ContextWatchers, key: &SubscriptionKey, context: &str, store: Option<&WatchCacheStore>) -> Option<usize> {
+    if let Some((size, _)) = context_watchers.list_sizes.get(key) {
+        return Some(*size);
+    }
+    if let Some(size) = store.filter(|_| key.persistable()).and_then(|store| store.list_size(&key.cache_id(context))) {
+        return Some(size);
+    }
+    let same_resource = |other: &SubscriptionKey| {
+        other.group == key.group && other.api_version == key.api_version && other.resource_plural == key.resource_plural
     };
+    let running = context_watchers.watchers.iter()
+        .filter(|(other, _)| same_resource(other))
+        .map(|(_, shared)| shared.cache.read().map(|c| c.len()).unwrap_or_default());
+    let stopped = context_watchers.list_sizes.iter()
+        .filter(|(other, _)| same_resource(other))
+        .map(|(_, (size, _))| *size);
+    running.chain(stopped).max()
+}
 
-    // Reads from KUBECONFIG env var or default location (~/.kube/config)
-    if let Ok(r) = Kubeconfig::read() {
-        match kci.merged {
-            Some(existing) => {
-                if let Ok(new_merged) = existing.clone().merge(r) {
-                    kci.merged = Some(new_merged);
-                } else {
-                    // if we can't merge, just keep the existing one
-                    kci.merged = Some(existing);
+/// Longest wait between retries, in case the backoff runs out
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Starts the source task that watches the API for `key` and feeds the shared broadcast and cache.
+/// Broadcast channels can't grow, so `capacity` is picked up front (see [broadcast_capacity]);
+/// bridges that still fall behind resync from the cache.
+/// With a `store`, the list is resumed from disk if it was watched before (see [resume_watch]).
+/// Lists stream their initial objects unless the connection's list strategy says the cluster can't, see [list_strategy].
+fn spawn_shared_watcher(
+    connection: &ClusterConnection,
+    key: &SubscriptionKey,
+    capacity: usize,
+    store: Option<(WatchCacheStore, String)>,
+    settings: &ListSettings,
+) -> SharedWatcher {
+    let (tx, first_receiver) = tokio::sync::broadcast::channel(capacity);
+    let view = WatcherView {
+        tx: tx.clone(),
+        cache: Arc::new(RwLock::new(ObjectCache::new(settings.strip_managed_fields))),
+        stale: Arc::new(AtomicBool::new(false)),
+        table_columns: Arc::new(RwLock::new(None)),
+        state: Arc::new(RwLock::new(WatchState::Relisting)),
+        filter: None,
+    };
+    let resource_version = Arc::new(RwLock::new(None));
+    let source_task = spawn_source_task(connection, key, view.clone(), resource_version.clone(), store, settings);
+
+    SharedWatcher {
+        tx,
+        first_receiver: Some(first_receiver),
+        cache: view.cache,
+        resource_version,
+        stale: view.stale,
+        table_columns: view.table_columns,
+        state: view.state,
+        source_task,
+        ref_count: 0,
+        idle_since: None,
+    }
+}
+
+/// Spawns the task behind a shared watcher, which lists and watches with the connection's client
+/// and feeds what it gets to the watcher's broadcast and cache (see [spawn_shared_watcher])
+fn spawn_source_task(
+    connection: &ClusterConnection,
+    key: &SubscriptionKey,
+    view: WatcherView,
+    resource_version: Arc<RwLock<Option<String>>>,
+    store: Option<(WatchCacheStore, String)>,
+    settings: &ListSettings,
+) -> TokioJoinHandle<()> {
+    let page_size = settings.page_size;
+    let health = HealthReporter::new(view.tx.clone(), view.state.clone(), connection.proxy_url.clone());
+    let client = connection.client.clone();
+    let list_strategy = connection.list_strategy.clone();
+
+    let ar = kube::discovery::ApiResource {
+        group: key.group.clone(),
+        api_version: key.api_version.clone(),
+        plural: key.resource_plural.clone(),
+        version: "".to_string(),
+        kind: "".to_string(),
+    };
+
+    // Determine which APIs to watch
+    let apis: Vec<Api<DynamicObject>> = if let Some(ns_list) = &key.namespaces {
+        // Watch multiple specific namespaces
+        if ns_list.is_empty() {
+            vec![Api::all_with(client.clone(), &ar)]
+        } else {
+            ns_list.iter()
+                .map(|ns| Api::namespaced_with(client.clone(), ns, &ar))
+                .collect()
+        }
+    } else if let Some(ns) = &key.namespace {
+        // Watch single namespace
+        vec![Api::namespaced_with(client.clone(), ns, &ar)]
+    } else {
+        // Watch all
+        vec![Api::all_with(client.clone(), &ar)]
+    };
+
+    // The namespace each API lists, None when one API lists everything the watcher has
+    let scopes: Vec<Option<String>> = match &key.namespaces {
+        Some(ns_list) if !ns_list.is_empty() => ns_list.iter().cloned().map(Some).collect(),
+        _ => vec![None],
+    };
+
+    let mut wc = watcher::Config::default();
+    if let Some(labels) = &key.label_selector {
+        wc = wc.labels(labels);
+    }
+    if let Some(fields) = &key.field_selector {
+        wc = wc.fields(fields);
+    }
+
+    let tx_clone = view.tx;
+    let cache_clone = view.cache;
+    let resource_version_clone = resource_version;
+    let stale_clone = view.stale;
+    let table_columns_clone = view.table_columns;
+    let name_clone = key.name.clone();
+    let metadata_only = key.metadata_only;
+    let as_table = key.as_table;
+
+    // Spawn Source Task
+    tokio::task::spawn(async move {
+         // We back off ourselves rather than with default_backoff, to tell the client how long for
+         let mut backoff = watcher::DefaultBackoff::default();
+         match name_clone {
+            Some(name) => {
+                // Single resource watch (always single API - name implies specific namespace usually or cluster scoped)
+                if let Some(first_api) = apis.first() {
+                    let mut events = watch_object(first_api.clone(), &name).boxed();
+                    loop {
+                        match events.try_next().await {
+                            Ok(Some(Some(event))) => {
+                                backoff.reset();
+                                health.set(WatchState::Connected);
+                                cache_apply(&cache_clone, &tx_clone, &event);
+                            },
+                             Ok(Some(None)) => {
+                                backoff.reset();
+                                health.set(WatchState::Connected);
+                                if let Ok(mut c) = cache_clone.write() {
+                                    c.clear();
+                                    let _ = tx_clone.send(ResourceListenEvent::SingleResourceNotFoundOrDeleted);
+                                }
+                            },
+                            Ok(None) => {
+                                let _ = tx_clone.send(ResourceListenEvent::Error {
+                                    message: "none value given from watcher".to_string()
+                                });
+                            }
+                            Err(e) => {
+                                let retry_in = backoff.next().unwrap_or(MAX_RETRY_DELAY);
+                                health.watch_failed(&e, retry_in);
+                                tokio::time::sleep(retry_in).await;
+                            }
+                        }
+                    }
                 }
             },
+            None if as_table => {
+                table::run_table_watch(apis, wc, tx_clone, &cache_clone, &table_columns_clone, &health).await;
+            },
             None => {
-                let context_names: Vec<String> = r.contexts.iter().map(|c|c.name.clone()).collect();
-                kci.merged = Some(r);
-                kci.sources.push(ContextSourceInfo {
-                    contexts: context_names,
-                    origin: KubeConfigOrigin::EnvironmentOrDefaultPath
-                });
+                // Start from the list we had on disk, if any (only single API lists are persisted)
+                if let (Some((store, id)), Some(api)) = (store, apis.first()) {
+                    let persisted = tokio::task::spawn_blocking(move || store.load(&id)).await.ok().flatten();
+                    if let Some(list) = persisted {
+                        resume_watch(api, list, &wc, &tx_clone, &cache_clone, &resource_version_clone, &stale_clone, &health).await;
+                    }
+                }
+
+                let mut strategy = list_strategy.resolve(&client).await;
+                'list: loop {
+                    // List/Watch
+                    // Merge streams if multiple APIs
+                    let wc = list_strategy::configure(wc.clone(), strategy, page_size);
+                    let streams = apis.iter().cloned().enumerate().map(|(stream, api)| {
+                         let events = if metadata_only {
+                             metadata_watcher(api, wc.clone()).map_ok(metadata_event_to_dynamic).boxed()
+                         } else {
+                             watcher(api, wc.clone()).boxed()
+                         };
+                         events.map(move |event| (stream, event)).boxed()
+                    });
+
+                    let mut events = futures_util::stream::select_all(streams);
+                    // The uids each stream listed since its Init, to drop the objects it no longer has on InitDone
+                    let mut listed: Vec<Option<HashSet<String>>> = vec![None; apis.len()];
+                    // Subscribers get one Init/InitDone pair, around the first list of every stream.
+                    // A stream that relists on its own later (its watch expired) sends its objects as InitApply
+                    // and the ones it lost as Delete, so subscribers keep the other streams' objects.
+                    let mut first_list_pending = vec![true; apis.len()];
+                    send_locked(&cache_clone, &tx_clone, ResourceListenEvent::Init);
+
+                    loop {
+                        match events.next().await {
+                            Some((_, Ok(Event::Apply(obj)))) => {
+                                backoff.reset();
+                                health.connected();
+                                track_resource_version(&resource_version_clone, &obj);
+                                // Updates go out as a patch against the cached version
+                                cache_apply(&cache_clone, &tx_clone, &obj);
+                            }
+                            Some((stream, Ok(p))) => {
+                                // Init comes before the list request, every other event means the server answered
+                                if !matches!(p, Event::Init) {
+                                    backoff.reset();
+                                }
+                                match &p {
+                                    Event::InitApply(obj) => track_resource_version(&resource_version_clone, obj),
+                                    Event::Delete(obj) => {
+                                        health.connected();
+                                        track_resource_version(&resource_version_clone, obj);
+                                    }
+                                    // A relist replaces whatever stale list we had
+                                    Event::Init => {
+                                        health.set(WatchState::Relisting);
+                                        stale_clone.store(false, Ordering::Relaxed);
+                                    }
+                                    Event::InitDone => {
+                                        if strategy == ListStrategy::Streaming {
+                                            list_strategy.confirm_streaming();
+                                        }
+                                        health.set(WatchState::Connected);
+                                    }
+                                }
+                                // Maintain Cache, the event shares the cached object
+                                match p {
+                                    Event::InitApply(obj) => {
+                                        if let (Some(seen), Some(uid)) = (listed[stream].as_mut(), &obj.metadata.uid) {
+                                            seen.insert(uid.clone());
+                                        }
+                                        cache_init_apply(&cache_clone, &tx_clone, &obj);
+                                    }
+                                    Event::Delete(obj) => cache_delete(&cache_clone, &tx_clone, &obj),
+                                    // Not cleared on Init, the other streams' objects stay, and so does the list until the new one is in
+                                    Event::Init => listed[stream] = Some(HashSet::new()),
+                                    Event::InitDone => {
+                                        if let Some(seen) = listed[stream].take() {
+                                            cache_prune(&cache_clone, &tx_clone, scopes[stream].as_deref(), &seen);
+                                        }
+                                        if std::mem::take(&mut first_list_pending[stream]) && !first_list_pending.contains(&true) {
+                                            send_locked(&cache_clone, &tx_clone, ResourceListenEvent::InitDone);
+                                        }
+                                    }
+                                    other => send_locked(&cache_clone, &tx_clone, ResourceListenEvent::from(other)),
+                                }
+                            }
+                            Some((_, Err(e))) if strategy == ListStrategy::Streaming && list_strategy::streaming_unsupported(&e) => {
+                                // Remembered for the cluster, later watchers list paginated right away
+                                eprintln!("Streaming lists unsupported, falling back to paginated lists: {}", e);
+                                list_strategy.fall_back();
+                                strategy = ListStrategy::Paginated;
+                                continue 'list;
+                            }
+                            Some((_, Err(e))) => {
+                                // The watcher relists by itself after a 410, the other errors are retried as they are
+                                let retry_in = backoff.next().unwrap_or(MAX_RETRY_DELAY);
+                                health.watch_failed(&e, retry_in);
+                                tokio::time::sleep(retry_in).await;
+                            },
+                            None => {
+                                // All streams finished?
+                                let _ = tx_clone.send(ResourceListenEvent::Error {
+                                    message: "all watcher streams ended".to_string()
+                                });
+                                break 'list;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Remembers the newest resourceVersion seen, so a later watcher can resume from it
+fn track_resource_version(resource_version: &RwLock<Option<String>>, obj: &DynamicObject) {
+    let Some(candidate) = &obj.metadata.resource_version else { return };
+    if let Ok(mut current) = resource_version.write() {
+        if watch_cache::is_newer(current.as_deref(), candidate) {
+            *current = Some(candidate.clone());
+        }
+    }
+}
+
+/// Sends a list persisted by an earlier watcher as stale data, then watches for changes since its resourceVersion.
+/// Returns once the watch can't go on (410 Gone if the version is too old, or any other error),
+/// and the caller relists.
+async fn resume_watch(
+    api: &Api<DynamicObject>,
+    list: PersistedList,
+    wc: &watcher::Config,
+    tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>,
+    cache: &RwLock<ObjectCache>,
+    resource_version: &RwLock<Option<String>>,
+    stale: &AtomicBool,
+    health: &HealthReporter,
+) {
+    eprintln!("Resuming {} objects from resourceVersion {}", list.objects.len(), list.resource_version);
+    stale.store(true, Ordering::Relaxed);
+    let objects = list.objects.into_iter()
+        .filter_map(|obj| {
+            let uid = obj["metadata"]["uid"].as_str()?.to_string();
+            Some((uid, Arc::unwrap_or_clone(obj)))
+        })
+        .collect();
+    if let Ok(mut rv) = resource_version.write() {
+        *rv = Some(list.resource_version.clone());
+    }
+    cache_replace(cache, tx, objects);
+    let _ = tx.send(ResourceListenEvent::Stale);
+
+    let (_, wp) = params_for(wc);
+    let wp = &wp;
+    let start = |version: String| async move { api.watch(wp, &version).await.map(StreamExt::boxed) };
+
+    let mark_live = || {
+        health.set(WatchState::Connected);
+        if stale.swap(false, Ordering::Relaxed) {
+            let _ = tx.send(ResourceListenEvent::Live);
+        }
+    };
+    let end = watch_until_failed(list.resource_version, start, |event| {
+        mark_live();
+        match event {
+            WatchEvent::Added(obj) | WatchEvent::Modified(obj) => {
+                track_resource_version(resource_version, &obj);
+                cache_apply(cache, tx, &obj);
+                obj.metadata.resource_version
+            }
+            WatchEvent::Deleted(obj) => {
+                track_resource_version(resource_version, &obj);
+                cache_delete(cache, tx, &obj);
+                obj.metadata.resource_version
+            }
+            WatchEvent::Bookmark(bookmark) => {
+                if let Ok(mut rv) = resource_version.write() {
+                    *rv = Some(bookmark.metadata.resource_version.clone());
+                }
+                Some(bookmark.metadata.resource_version)
+            }
+            WatchEvent::Error(_) => None,
+        }
+    }).await;
+
+    match end {
+        // The version was compacted away
+        WatchEnd::Expired => {
+            eprintln!("Resumed watch expired, relisting");
+            health.set(WatchState::Desynced);
+        }
+        WatchEnd::Failed(message) => eprintln!("Resumed watch failed, relisting: {}", message),
+        WatchEnd::RequestFailed(e) => eprintln!("Resumed watch failed, relisting: {}", e),
+    }
+}
+
+/// The label and field selectors of a watcher config, for lists and watches made without a watcher
+fn params_for(wc: &watcher::Config) -> (ListParams, WatchParams) {
+    let (mut lp, mut wp) = (ListParams::default(), WatchParams::default());
+    if let Some(labels) = &wc.label_selector {
+        lp = lp.labels(labels);
+        wp = wp.labels(labels);
+    }
+    if let Some(fields) = &wc.field_selector {
+        lp = lp.fields(fields);
+        wp = wp.fields(fields);
+    }
+    (lp, wp)
+}
+
+/// Why a watch run by [watch_until_failed] stopped
+enum WatchEnd {
+    /// 410 Gone, the list has to be fetched again
+    Expired,
+    /// The server sent an error event
+    Failed(String),
+    /// The request failed, which may be the connection through a proxy
+    RequestFailed(kube::Error),
+}
+
+/// Watches from `resource_version` until the watch fails, with `start` making the watch request from a version.
+/// `on_event` gets every event but errors, and returns the resourceVersion it carries, if any.
+async fn watch_until_failed<T, S, F>(
+    mut resource_version: String,
+    start: impl Fn(String) -> F,
+    mut on_event: impl FnMut(WatchEvent<T>) -> Option<String>,
+) -> WatchEnd
+where
+    S: futures_util::Stream<Item = Result<WatchEvent<T>, kube::Error>> + Unpin,
+    F: std::future::Future<Output = Result<S, kube::Error>>,
+{
+    loop {
+        let mut events = match start(resource_version.clone()).await {
+            Ok(events) => events,
+            Err(e) => return WatchEnd::RequestFailed(e),
+        };
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(WatchEvent::Error(e)) if e.code == 410 => return WatchEnd::Expired,
+                Ok(WatchEvent::Error(e)) => return WatchEnd::Failed(format!("{} ({})", e.message, e.code)),
+                Ok(event) => {
+                    if let Some(version) = on_event(event) {
+                        resource_version = version;
+                    }
+                }
+                Err(e) => return WatchEnd::RequestFailed(e),
+            }
+        }
+        // The server ends watches after a few minutes, pick up where we left off
+    }
+}
+
+/// A snapshot of the watcher's list to save, if it can be resumed later
+fn persisted_list(context: &str, key: &SubscriptionKey, shared: &SharedWatcher) -> Option<PersistedList> {
+    if !key.persistable() {
+        return None;
+    }
+    let resource_version = shared.resource_version.read().ok()?.clone()?;
+    let objects: Vec<SharedObject> = shared.cache.read().ok()?.values().cloned().collect();
+    Some(PersistedList {
+        id: key.cache_id(context),
+        context: context.to_string(),
+        resource_version,
+        objects,
+    })
+}
+
+/// Writes a watcher's list to disk in the background, for the next watcher of the same list to start from
+fn persist_watcher(store: Option<&WatchCacheStore>, context: &str, key: &SubscriptionKey, shared: &SharedWatcher) {
+    let Some(store) = store else { return };
+    let Some(list) = persisted_list(context, key, shared) else { return };
+    let store = store.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = store.save(&list) {
+            eprintln!("Failed to persist watch cache for {}: {}", list.id, e);
+        }
+    });
+}
+
+/// Saves the lists of every watcher, running or idle, before the app exits.
+/// Blocks, since the runtime may not run spawned tasks anymore.
+fn persist_all_watchers(app: &AppHandle) {
+    let state = app.state::<GlobalState>();
+    let Some(store) = &state.watch_cache else { return };
+    let watchers = state.watchers.blocking_lock();
+    for (context, context_watchers) in watchers.iter() {
+        for (key, shared) in &context_watchers.watchers {
+            let Some(list) = persisted_list(context, key, shared) else { continue };
+            if let Err(e) = store.save(&list) {
+                eprintln!("Failed to persist watch cache for {}: {}", list.id, e);
+            }
+        }
+    }
+}
+
+/// Broadcasts an event about the cache's contents while holding its lock.
+/// Every change to the cache goes out this way, so a bridge that snapshots the cache (see [replay_cache])
+/// receives exactly the events that came after its snapshot, and never applies a patch twice.
+fn send_locked(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, event: ResourceListenEvent) {
+    let _cache = cache.write();
+    let _ = tx.send(event);
+}
+
+/// Stores an updated object in the cache and broadcasts it:
+/// a [ResourceListenEvent::Patch] against the cached version if there is one, the whole object otherwise.
+/// Nothing is sent if the object didn't change.
+fn cache_apply(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, obj: &DynamicObject) {
+    let Ok(resource) = serde_json::to_value(obj) else { return };
+    match obj.metadata.uid.clone() {
+        Some(uid) => cache_apply_value(cache, tx, uid, resource),
+        None => send_locked(cache, tx, ResourceListenEvent::Apply { resource: Arc::new(resource) }),
+    }
+}
+
+/// [cache_apply] for an object that's already JSON
+fn cache_apply_value(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, uid: String, resource: serde_json::Value) {
+    let Ok(mut c) = cache.write() else {
+        let _ = tx.send(ResourceListenEvent::Apply { resource: Arc::new(resource) });
+        return;
+    };
+
+    let resource = c.ingest(resource);
+    let event = match c.insert(uid.clone(), resource.clone()) {
+        Some(previous) => {
+            let ops = json_patch::diff(&previous, &resource);
+            if ops.0.is_empty() {
+                return;
+            }
+            ResourceListenEvent::Patch { uid, ops }
+        }
+        None => ResourceListenEvent::Apply { resource },
+    };
+    let _ = tx.send(event);
+}
+
+/// Stores an object of a (re)list in the cache, and broadcasts the InitApply event for it
+fn cache_init_apply(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, obj: &DynamicObject) {
+    let Ok(resource) = serde_json::to_value(obj) else { return };
+    let Ok(mut c) = cache.write() else { return };
+    let resource = c.ingest(resource);
+    if let Some(uid) = &obj.metadata.uid {
+        c.insert(uid.clone(), resource.clone());
+    }
+    let _ = tx.send(ResourceListenEvent::InitApply { resource });
+}
+
+/// Removes a deleted object from the cache, and broadcasts the Delete event for it
+fn cache_delete(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, obj: &DynamicObject) {
+    let Ok(resource) = serde_json::to_value(obj) else { return };
+    cache_delete_value(cache, tx, obj.metadata.uid.as_deref(), resource);
+}
+
+/// [cache_delete] for an object that's already JSON
+fn cache_delete_value(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, uid: Option<&str>, resource: serde_json::Value) {
+    let Ok(mut c) = cache.write() else {
+        let _ = tx.send(ResourceListenEvent::Delete { resource: Arc::new(resource) });
+        return;
+    };
+    if let Some(uid) = uid {
+        c.remove(uid);
+    }
+    let resource = c.ingest(resource);
+    let _ = tx.send(ResourceListenEvent::Delete { resource });
+}
+
+/// Removes the objects a relist didn't return (e.g. deleted while a persisted list was on disk),
+/// and broadcasts their deletion. With `namespace`, only that namespace's objects were relisted.
+fn cache_prune(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, namespace: Option<&str>, listed: &HashSet<String>) {
+    let Ok(mut c) = cache.write() else { return };
+    let removed = c.remove_where(|uid, resource| {
+        !listed.contains(uid) && namespace.is_none_or(|ns| resource["metadata"]["namespace"].as_str() == Some(ns))
+    });
+    if !removed.is_empty() {
+        eprintln!("Relist dropped {} objects that no longer exist", removed.len());
+    }
+    for resource in removed {
+        let _ = tx.send(ResourceListenEvent::Delete { resource });
+    }
+}
+
+/// Replaces the cache with a fresh list and broadcasts it as Init, InitApply for every object, InitDone
+fn cache_replace(cache: &RwLock<ObjectCache>, tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>, objects: Vec<(String, serde_json::Value)>) {
+    let Ok(mut c) = cache.write() else { return };
+    let _ = tx.send(ResourceListenEvent::Init);
+    for resource in c.replace(objects) {
+        let _ = tx.send(ResourceListenEvent::InitApply { resource });
+    }
+    let _ = tx.send(ResourceListenEvent::InitDone);
+}
+
+/// Converts a metadata-only event into the same shape as a full watch event,
+/// with no fields besides apiVersion, kind and metadata.
+/// managedFields are dropped too, they are usually the biggest part of the metadata.
+fn metadata_event_to_dynamic(event: Event<PartialObjectMeta<DynamicObject>>) -> Event<DynamicObject> {
+    let convert = |mut meta: PartialObjectMeta<DynamicObject>| {
+        meta.metadata.managed_fields = None;
+        DynamicObject {
+            types: meta.types,
+            metadata: meta.metadata,
+            data: serde_json::json!({}),
+        }
+    };
+    match event {
+        Event::Init => Event::Init,
+        Event::InitDone => Event::InitDone,
+        Event::InitApply(meta) => Event::InitApply(convert(meta)),
+        Event::Apply(meta) => Event::Apply(convert(meta)),
+        Event::Delete(meta) => Event::Delete(convert(meta)),
+    }
+}
+
+/// Finds a running watcher whose list contains everything `key` asks for, preferring the smallest list.
+/// Watchers that can't list (e.g. forbidden cluster-wide, where `key` may be allowed in its namespace) are skipped.
+fn find_superset_watcher(context_watchers: &ContextWatchers, key: &SubscriptionKey) -> Option<(SubscriptionKey, SubsetFilter)> {
+    context_watchers.watchers.iter()
+        .filter(|(_, shared)| {
+            shared.state.read().is_ok_and(|state| !matches!(*state, WatchState::Forbidden { .. } | WatchState::BackingOff { .. }))
+        })
+        .filter_map(|(broad, shared)| {
+            let filter = subset::covering_filter(broad, key)?;
+            let size = shared.cache.read().map(|c| c.len()).unwrap_or_default();
+            Some((size, broad, filter))
+        })
+        .min_by_key(|(size, _, _)| *size)
+        .map(|(_, broad, filter)| (broad.clone(), filter))
+}
+
+/// Returns the shared watcher for `key` on a context and takes a reference on it.
+/// A watcher for exactly `key` is preferred, then one for a superset of it (see [subset::covering_filter]),
+/// and only then is a new one started.
+/// The returned flag is true if the watcher was just started.
+fn attach_shared_watcher<'a>(
+    connection: &ClusterConnection,
+    context_watchers: &'a mut ContextWatchers,
+    key: &SubscriptionKey,
+    subscription_id: i32,
+    context: &str,
+    store: Option<&WatchCacheStore>,
+    settings: &ListSettings,
+) -> (&'a mut SharedWatcher, bool, WatcherSource) {
+    let superset = if context_watchers.watchers.contains_key(key) { None } else { find_superset_watcher(context_watchers, key) };
+    if let Some((broad, filter)) = superset {
+        eprintln!("[{}] Serving key {:?} on {} from the watcher for {:?}", subscription_id, key, context, broad);
+        let shared = context_watchers.watchers.get_mut(&broad).unwrap();
+        shared.ref_count += 1;
+        shared.idle_since = None;
+        let source = WatcherSource { context: context.to_string(), key: broad, filter: Some(Arc::new(filter)) };
+        return (shared, false, source);
+    }
+
+    let is_new = !context_watchers.watchers.contains_key(key);
+    if is_new {
+        let capacity = broadcast_capacity(known_list_size(context_watchers, key, context, store));
+        let store = store
+            .filter(|_| key.persistable())
+            .map(|store| (store.clone(), key.cache_id(context)));
+        let shared = spawn_shared_watcher(connection, key, capacity, store, settings);
+        eprintln!("[{}] Started NEW source task for key {:?} on {} (capacity {})", subscription_id, key, context, capacity);
+        context_watchers.watchers.insert(key.clone(), shared);
+    } else {
+        eprintln!("[{}] Reusing existing source task for key {:?} on {}", subscription_id, key, context);
+    }
+
+    let shared = context_watchers.watchers.get_mut(key).unwrap();
+    shared.ref_count += 1;
+    shared.idle_since = None;
+    let source = WatcherSource { context: context.to_string(), key: key.clone(), filter: None };
+    (shared, is_new, source)
+}
+
+const DEFAULT_BATCH_WINDOW_MS: u64 = 50;
+const DEFAULT_BATCH_MAX_EVENTS: usize = 500;
+
+/// How a bridge groups events before sending them over IPC.
+/// Events are held back for at most `window` after the first one, or until `max_events` are pending.
+#[derive(Clone, Copy, Debug)]
+struct BatchConfig {
+    window: Duration,
+    max_events: usize,
+}
+
+impl BatchConfig {
+    /// A window of 0 ms or a maximum of 1 event sends every event on its own
+    fn new(window_ms: Option<u64>, max_events: Option<usize>) -> Self {
+        BatchConfig {
+            window: Duration::from_millis(window_ms.unwrap_or(DEFAULT_BATCH_WINDOW_MS)),
+            max_events: max_events.unwrap_or(DEFAULT_BATCH_MAX_EVENTS),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.window.is_zero() && self.max_events > 1
+    }
+}
+
+/// Collects events for one client channel and sends them as [ResourceListenEvent::Batch]es
+struct EventBatcher<F: Fn(ResourceListenEvent) -> ResourceListenEvent> {
+    channel: Channel<ResourceListenEvent>,
+    config: BatchConfig,
+    wrap: F,
+    pending: Vec<ResourceListenEvent>,
+    /// When the pending events have to go out
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl<F: Fn(ResourceListenEvent) -> ResourceListenEvent> EventBatcher<F> {
+    fn new(channel: Channel<ResourceListenEvent>, config: BatchConfig, wrap: F) -> Self {
+        EventBatcher { channel, config, wrap, pending: Vec::new(), deadline: None }
+    }
+
+    /// Queues an event, returns false if sending to the client failed
+    fn push(&mut self, event: ResourceListenEvent) -> bool {
+        let event = (self.wrap)(event);
+        if !self.config.enabled() {
+            return self.channel.send(event).is_ok();
+        }
+        if self.pending.is_empty() {
+            self.deadline = Some(tokio::time::Instant::now() + self.config.window);
+        }
+        self.pending.push(event);
+        if self.pending.len() >= self.config.max_events {
+            return self.flush();
+        }
+        true
+    }
+
+    /// Sends everything pending, returns false if sending to the client failed
+    fn flush(&mut self) -> bool {
+        self.deadline = None;
+        let mut events = std::mem::take(&mut self.pending);
+        let result = match events.len() {
+            0 => return true,
+            1 => self.channel.send(events.pop().unwrap()),
+            _ => self.channel.send(ResourceListenEvent::Batch { events }),
+        };
+        result.is_ok()
+    }
+}
+
+/// Sends a snapshot of the cache as Init, InitApply for every object, InitDone,
+/// so the client drops anything not in it. Table subscriptions get their columns first,
+/// Stale follows if the snapshot came from disk and hasn't caught up yet, then the watcher's state.
+/// `rx` is replaced by a receiver that starts right after the snapshot (see [send_locked]).
+/// Returns false if sending to the client failed.
+fn replay_cache<F: Fn(ResourceListenEvent) -> ResourceListenEvent>(
+    batcher: &mut EventBatcher<F>,
+    view: &WatcherView,
+    subset: Option<&mut SubsetStream>,
+    rx: &mut tokio::sync::broadcast::Receiver<ResourceListenEvent>,
+) -> bool {
+    // Copy the snapshot so the lock isn't held while sending
+    let mut snapshot: Vec<SharedObject> = match view.cache.read() {
+        Ok(cache) => {
+            // Whatever was broadcast before is in the snapshot, whatever comes after isn't
+            *rx = view.tx.subscribe();
+            cache.values().cloned().collect()
+        }
+        Err(_) => Vec::new(),
+    };
+    let mut not_found = None;
+    if let Some(subset) = subset {
+        snapshot = subset.snapshot(snapshot);
+        not_found = subset.not_found();
+    }
+    let columns = view.table_columns.read().ok().and_then(|c| c.clone());
+
+    let mut open = true;
+    if let Some(columns) = columns {
+        open = batcher.push(ResourceListenEvent::TableColumns { columns });
+    }
+    open = open && batcher.push(ResourceListenEvent::Init);
+    for resource in snapshot {
+        open = open && batcher.push(ResourceListenEvent::InitApply { resource });
+    }
+    open = open && batcher.push(ResourceListenEvent::InitDone);
+    if let Some(not_found) = not_found {
+        open = open && batcher.push(not_found);
+    }
+    if view.stale.load(Ordering::Relaxed) {
+        open = open && batcher.push(ResourceListenEvent::Stale);
+    }
+    if let Some(state) = view.state.read().ok().map(|s| s.clone()) {
+        open = open && batcher.push(ResourceListenEvent::State { state });
+    }
+    open
+}
+
+/// Forwards a shared watcher's broadcast to a client channel, passing every event through `wrap`
+async fn run_bridge(
+    mut rx: tokio::sync::broadcast::Receiver<ResourceListenEvent>,
+    view: WatcherView,
+    is_new: bool,
+    channel: Channel<ResourceListenEvent>,
+    batch_config: BatchConfig,
+    mut resync: tokio::sync::watch::Receiver<()>,
+    wrap: impl Fn(ResourceListenEvent) -> ResourceListenEvent,
+) {
+    let mut batcher = EventBatcher::new(channel, batch_config, wrap);
+    // Served from a broader watcher, only part of its events are ours
+    let mut subset = view.filter.clone().map(SubsetStream::new);
+
+    // Only perform artificial replay if we are joining an EXISTING stream.
+    // If it's NEW, the source task will naturally emit Init/InitDone to the channel.
+    if !is_new {
+        replay_cache(&mut batcher, &view, subset.as_mut(), &mut rx);
+    }
+
+    // D. Loop Broadcast
+    loop {
+        let deadline = batcher.deadline;
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(msg) => {
+                    let events = match subset.as_mut() {
+                        Some(subset) => subset.apply(msg, &view.cache),
+                        None => vec![msg],
+                    };
+                    // Send to Tauri channel
+                    if !events.into_iter().all(|event| batcher.push(event)) {
+                        // Sending failed, e.g. the webview is gone
+                        break;
+                    }
+                },
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                    // Events were dropped, so the client's list can't be patched up incrementally.
+                    // Resync it from the cache, which already has everything we missed
+                    eprintln!("Bridge task lagged by {} messages, replaying cache", n);
+                    if !replay_cache(&mut batcher, &view, subset.as_mut(), &mut rx) {
+                        break;
+                    }
+                },
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                    batcher.flush();
+                    break;
+                }
+            },
+            changed = resync.changed() => {
+                // The client lost track of the list, e.g. a patch didn't apply
+                if changed.is_err() || !(batcher.flush() && replay_cache(&mut batcher, &view, subset.as_mut(), &mut rx)) {
+                    break;
+                }
+            },
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                if !batcher.flush() {
+                    break;
+                }
             }
         }
     }
-    let mut state = ctx.lock().await;
+}
 
-    state.kubeconfig = kci.merged.clone();
-    Ok(kci)
+/// Runs the bridge of a subscription, and releases the subscription once the bridge stops by itself:
+/// the shared watcher went away, or sending to the client failed.
+/// A channel the page dropped doesn't make sends fail, so this isn't how subscriptions end:
+/// the client stops them with `stop_listen_task` when it's done, and a reload stops them all (see [release_all_tasks]).
+fn spawn_subscription(app: AppHandle, subscription_id: i32, bridge: impl std::future::Future<Output = ()> + Send + 'static) -> TokioJoinHandle<()> {
+    tokio::task::spawn(async move {
+        bridge.await;
+        eprintln!("[{}] Bridge stopped by itself", subscription_id);
+        // On another task, releasing aborts this one
+        tokio::task::spawn(async move {
+            release_task(&app.state::<GlobalState>(), subscription_id).await;
+        });
+    })
 }
 
-/// Sets our state to use the client's desired kubeconfig
-/// (usually selected from the list provided by list_kube_contexts)
-#[tauri::command]
-async fn start(ctx: CommandGlobalState<'_>, context_name: String) -> Result<(), String> {
-    let mut state = ctx.lock().await;
-
-    kill_all_tasks(&mut state);
-    match &state.kubeconfig {
-        Some(kubeconfig) => {
-            state.kube_client = Client::try_from(Config::from_custom_kubeconfig(kubeconfig.clone(), &KubeConfigOptions {
-                context: Some(context_name),
-                cluster: None,
-                user: None,
-            }).await.unwrap()).map_err(|_| "invalid kubeconfig".to_string())?;
-            Ok(())
-        }
-        None => {
-            Err("kubeconfig not ready".parse().unwrap())
-        }
+/// Stops the subscriptions with IDs up to `last_id`, for when the webview reloads and all their channels are gone.
+/// The new page's subscriptions get later IDs, and may start before this gets to run.
+async fn release_all_tasks(app: &AppHandle, last_id: i32) {
+    let state = app.state::<GlobalState>();
+    let task_ids: Vec<i32> = state.task_map.lock().await.keys().copied().filter(|id| *id <= last_id).collect();
+    if !task_ids.is_empty() {
+        eprintln!("Webview reloaded, releasing {} subscriptions", task_ids.len());
+    }
+    for task_id in task_ids {
+        release_task(&state, task_id).await;
     }
 }
 
-/// Retrieves detailed information about a specific Kubernetes resource.
+/// Subscribes to a resource list, or a single resource if `name` is set.
+/// Returns the subscription ID to pass to `stop_listen_task`.
 #[tauri::command]
-async fn detail_resource(
+async fn start_listening(
+    app: AppHandle,
     state: CommandGlobalState<'_>,
     group: String,
     api_version: String,
     resource_plural: String,
-    name: String,
+    name: Option<String>,
     namespace: Option<String>,
-) -> Result<serde_json::Value, String> {
-    let state = state.lock().await;
-
-    let ar = kube::discovery::ApiResource {
-        group,
-        api_version,
-        plural: resource_plural,
-        version: "".to_string(),
-        kind: "".to_string(),
-    };
-    let api: Api<DynamicObject> = match namespace {
-        Some(ns) => Api::namespaced_with(state.kube_client.clone(), &ns, &ar),
-        None => Api::all_with(state.kube_client.clone(), &ar),
+    namespaces: Option<Vec<String>>,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    metadata_only: Option<bool>,
+    as_table: Option<bool>,
+    context: Option<String>,
+    batch_window_ms: Option<u64>,
+    batch_max_events: Option<usize>,
+    channel: Channel<ResourceListenEvent>
+) -> Result<i32, CommandError> {
+    // 1. Construct the key
+    let key = SubscriptionKey::new(group, api_version, resource_plural, name, namespace, namespaces, label_selector, field_selector, metadata_only, as_table);
+
+    let context = state.context_name(context).await?;
+    let connection = state.connection(Some(context.clone())).await?;
+    let list_settings = state.list_settings.read().await.clone();
+    let subscription_id = state.allocate_task_id();
+
+    // 2. Check or Create Source Task, 3. Increment Ref Count & attach
+    let (rx, view, is_new, source) = {
+        let mut watchers = state.watchers.lock().await;
+        let context_watchers = watchers.get_mut(&context).ok_or(CommandError::NotConnected)?;
+        let (shared, is_new, source) = attach_shared_watcher(&connection, context_watchers, &key, subscription_id, &context, state.watch_cache.as_ref(), &list_settings);
+        (shared.subscribe(), shared.view(source.filter.clone()), is_new, source)
     };
 
-    let obj = api.get(&name).await.map_err(|e| e.to_string())?;
-    serde_json::to_value(obj).map_err(|e| e.to_string())
-}
+    // 4. Spawn Bridge Task, registered before it can stop by itself so that its release finds it
+    let (resync_tx, resync_rx) = tokio::sync::watch::channel(());
+    let mut task_map = state.task_map.lock().await;
+    let bridge_handle = spawn_subscription(app, subscription_id, run_bridge(
+        rx,
+        view,
+        is_new,
+        channel,
+        BatchConfig::new(batch_window_ms, batch_max_events),
+        resync_rx,
+        |e| e,
+    ));
 
-fn kill_all_tasks(
-    state: &mut tokio::sync::MutexGuard<GlobalState>,
-) {
-    let task_ids: Vec<i32> = state.task_map.keys().cloned().collect();
-    for task_id in task_ids {
-        kill_task_internal(state, task_id);
-    }
+    // 5. Register Bridge Task in task_map
+    task_map.insert(subscription_id, TaskHandle {
+        handle: bridge_handle,
+        metadata: key.metadata(subscription_id, vec![context]),
+        sources: vec![source],
+        query: None,
+        resync: Some(resync_tx),
+        context_bridges: HashMap::new(),
+    });
+
+    Ok(subscription_id)
 }
 
-fn kill_task_internal(
-    state: &mut tokio::sync::MutexGuard<GlobalState>,
-    task_id: i32
-) -> Option<()> {
-    match state.task_map.remove(&task_id) {
-        Some(task_handle) => {
-            eprintln!("[{}] Stopping task with handle {}", task_id, task_handle.handle.id());
-            TokioJoinHandle::abort(&task_handle.handle);
-            Some(())
+/// Watches the same resource type across several contexts and merges them into one stream.
+/// Every event is wrapped in [ResourceListenEvent::FromContext] so the client knows which cluster it came from.
+/// Contexts that aren't connected yet are connected first, all at once.
+/// A context that can't be connected gets an Error event, and the subscription goes on with the others
+/// (unless none could be, then the first error is returned).
+#[tauri::command]
+async fn start_listening_composed(
+    app: AppHandle,
+    state: CommandGlobalState<'_>,
+    group: String,
+    api_version: String,
+    resource_plural: String,
+    contexts: Vec<String>,
+    namespaces: Option<Vec<String>>,
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    metadata_only: Option<bool>,
+    batch_window_ms: Option<u64>,
+    batch_max_events: Option<usize>,
+    channel: Channel<ResourceListenEvent>
+) -> Result<i32, CommandError> {
+    let key = SubscriptionKey::new(group, api_version, resource_plural, None, None, namespaces, label_selector, field_selector, metadata_only, None);
+
+    let mut contexts = contexts;
+    let mut seen = HashSet::new();
+    contexts.retain(|context| seen.insert(context.clone()));
+
+    let results = futures_util::future::join_all(contexts.iter().map(|context| state.connect(context))).await;
+    let mut connections = Vec::new();
+    let mut failed = Vec::new();
+    for (context, result) in contexts.iter().zip(results) {
+        match result {
+            Ok(connection) => connections.push((context.clone(), connection)),
+            Err(e) => failed.push((context.clone(), e)),
         }
-        None => {
-            eprintln!("[{}] No task found to abort", task_id);
-            None
+    }
+
+    let batch_config = BatchConfig::new(batch_window_ms, batch_max_events);
+    let list_settings = state.list_settings.read().await.clone();
+    let subscription_id = state.allocate_task_id();
+    let mut bridges = Vec::new();
+    let mut context_bridges = HashMap::new();
+    let mut sources = Vec::new();
+    let (resync_tx, resync_rx) = tokio::sync::watch::channel(());
+    let mut watchers = state.watchers.lock().await;
+    // Disconnected since, or never connected
+    connections.retain(|(context, _)| {
+        let connected = watchers.contains_key(context);
+        if !connected {
+            failed.push((context.clone(), CommandError::NotConnected));
         }
+        connected
+    });
+    if connections.is_empty() && !failed.is_empty() {
+        return Err(failed.remove(0).1);
+    }
+    for (context, connection) in &connections {
+        let Some(context_watchers) = watchers.get_mut(context) else { continue };
+        let (shared, is_new, source) = attach_shared_watcher(connection, context_watchers, &key, subscription_id, context, state.watch_cache.as_ref(), &list_settings);
+        let rx = shared.subscribe();
+        let view = shared.view(source.filter.clone());
+        sources.push(source);
+
+        let source_context = context.clone();
+        let bridge = run_bridge(rx, view, is_new, channel.clone(), batch_config, resync_rx.clone(), move |event| {
+            ResourceListenEvent::FromContext {
+                context: source_context.clone(),
+                event: Box::new(event),
+            }
+        });
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+        let handle = tokio::task::spawn(run_context_bridge(bridge, stop_rx, channel.clone(), context.clone()));
+        context_bridges.insert(context.clone(), ContextBridge { stop: stop_tx, handle: handle.abort_handle() });
+        bridges.push(handle);
+    }
+    drop(watchers);
+
+    // Ends once every context's bridge has
+    let mut task_map = state.task_map.lock().await;
+    let bridge_handle = spawn_subscription(app, subscription_id, async move {
+        futures_util::future::join_all(bridges).await;
+    });
+
+    task_map.insert(subscription_id, TaskHandle {
+        handle: bridge_handle,
+        metadata: key.metadata(subscription_id, connections.into_iter().map(|(context, _)| context).collect()),
+        sources,
+        query: None,
+        resync: Some(resync_tx),
+        context_bridges,
+    });
+
+    for (context, e) in failed {
+        eprintln!("[{}] Not watching {}: {}", subscription_id, context, e);
+        let event = ResourceListenEvent::Error { message: e.to_string() };
+        let _ = channel.send(ResourceListenEvent::FromContext { context, event: Box::new(event) });
     }
+    Ok(subscription_id)
 }
-/// Stops a running subscription by aborting the task associated with it.
-#[tauri::command]
-async fn stop_listen_task(
-    state: CommandGlobalState<'_>,
-    task_id: i32
-) -> Result<(), String> {
-    let mut state = state.lock().await;
 
-    // 1. Remove Bridge Task
-    if let Some(task_handle) = state.task_map.remove(&task_id) {
-         eprintln!("[{}] Stopping bridge task", task_id);
-         TokioJoinHandle::abort(&task_handle.handle);
-
-         // 2. Decrement Ref Count on Shared Watcher
-         let key = SubscriptionKey {
-             group: task_handle.metadata.group,
-             api_version: task_handle.metadata.api_version,
-             resource_plural: task_handle.metadata.resource_plural,
-             namespace: task_handle.metadata.namespace,
-             name: task_handle.metadata.name,
-             namespaces: task_handle.metadata.namespaces,
-         };
-
-         if let Some(shared) = state.watchers.get_mut(&key) {
-             shared.ref_count -= 1;
-             eprintln!("[{}] Decremented ref count for {:?} to {}", task_id, key, shared.ref_count);
-             if shared.ref_count == 0 {
-                 eprintln!("[{}] Stopping source task for {:?}", task_id, key);
-                 TokioJoinHandle::abort(&shared.source_task);
-                 state.watchers.remove(&key);
-             }
-         }
-         Ok(())
-    } else {
-        Err("no such task".to_string())
+/// Runs the bridge of one context of a composed subscription.
+/// When the context is disconnected, the client gets an empty list for it and the other contexts carry on.
+async fn run_context_bridge(
+    bridge: impl std::future::Future<Output = ()>,
+    stop: tokio::sync::oneshot::Receiver<()>,
+    channel: Channel<ResourceListenEvent>,
+    context: String,
+) {
+    tokio::select! {
+        _ = bridge => {}
+        Ok(()) = stop => {
+            for event in [ResourceListenEvent::Init, ResourceListenEvent::InitDone] {
+                let _ = channel.send(ResourceListenEvent::FromContext { context: context.clone(), event: Box::new(event) });
+            }
+        }
     }
 }
 
-#[derive(Clone, Serialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct TaskMetadata {
-    id: i32,
-    group: String,
-    api_version: String,
-    resource_plural: String,
-    name: Option<String>,
-    namespace: Option<String>,
-    namespaces: Option<Vec<String>>,
+/// The cache a subscription reads from on one context
+struct SubscriptionCache {
+    context: String,
+    cache: Arc<RwLock<ObjectCache>>,
+    /// The subscription's part of the cache, if its watcher is a broader one
+    filter: Option<Arc<SubsetFilter>>,
 }
 
-struct TaskHandle {
-    handle: TokioJoinHandle<()>,
-    metadata: TaskMetadata
+/// The caches of the shared watchers a subscription reads from, by context
+async fn subscription_caches(state: &GlobalState, subscription_id: i32) -> Result<Vec<SubscriptionCache>, CommandError> {
+    let sources = state.task_map.lock().await
+        .get(&subscription_id)
+        .map(|task| task.sources.clone())
+        .ok_or(CommandError::SubscriptionNotFound { id: subscription_id })?;
+    let watchers = state.watchers.lock().await;
+    Ok(sources.iter()
+        .filter_map(|source| {
+            let shared = watchers.get(&source.context)?.watchers.get(&source.key)?;
+            Some(SubscriptionCache { context: source.context.clone(), cache: shared.cache.clone(), filter: source.filter.clone() })
+        })
+        .collect())
+}
+
+/// Filters, sorts and pages the objects in the caches
+fn query_caches(query: &CompiledQuery, caches: &[SubscriptionCache]) -> QueryWindow {
+    let guards: Vec<_> = caches.iter()
+        .filter_map(|c| c.cache.read().ok().map(|guard| (c.context.as_str(), guard, c.filter.as_deref())))
+        .collect();
+    query.window(guards.iter().flat_map(|(context, guard, filter)| {
+        guard.values()
+            .filter(move |resource| filter.is_none_or(|f| f.matches(resource)))
+            .map(move |resource| (*context, resource.as_ref()))
+    }))
 }
 
+/// Returns a sorted, filtered window of the objects an open subscription has received so far
 #[tauri::command]
-async fn start_listening(
+async fn query_resources(state: CommandGlobalState<'_>, subscription_id: i32, query: ListQuery) -> Result<QueryWindow, CommandError> {
+    let query = query.compile().map_err(|message| CommandError::InvalidQuery { message })?;
+    let caches = subscription_caches(&state, subscription_id).await?;
+    Ok(query_caches(&query, &caches))
+}
+
+/// How long a window subscription waits for more changes before recomputing the window
+const WINDOW_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Like `start_listening`, but instead of every event the client gets the window selected by `query`,
+/// whenever a change affects it. The window can be moved with `set_window_query`.
+#[tauri::command]
+async fn start_listening_window(
+    app: AppHandle,
     state: CommandGlobalState<'_>,
     group: String,
     api_version: String,
     resource_plural: String,
-    subscription_id: i32,
-    name: Option<String>,
     namespace: Option<String>,
     namespaces: Option<Vec<String>>,
-    channel: Channel<ResourceListenEvent>
-) -> Result<i32, String> {
-    
-    // Sort namespaces if present to ensure consistent key
-    let mut sorted_namespaces = namespaces.clone();
-    if let Some(ref mut ns_list) = sorted_namespaces {
-        ns_list.sort();
-    }
-
-    // 1. Construct the key
-    let key = SubscriptionKey {
-        group: group.clone(),
-        api_version: api_version.clone(),
-        resource_plural: resource_plural.clone(),
-        namespace: namespace.clone(),
-        name: name.clone(),
-        namespaces: sorted_namespaces.clone(),
+    label_selector: Option<String>,
+    field_selector: Option<String>,
+    metadata_only: Option<bool>,
+    context: Option<String>,
+    query: ListQuery,
+    channel: Channel<QueryWindow>
+) -> Result<i32, CommandError> {
+    let query = query.compile().map_err(|message| CommandError::InvalidQuery { message })?;
+    let key = SubscriptionKey::new(group, api_version, resource_plural, None, namespace, namespaces, label_selector, field_selector, metadata_only, None);
+
+    let context = state.context_name(context).await?;
+    let connection = state.connection(Some(context.clone())).await?;
+    let list_settings = state.list_settings.read().await.clone();
+    let subscription_id = state.allocate_task_id();
+
+    let (rx, caches, source) = {
+        let mut watchers = state.watchers.lock().await;
+        let context_watchers = watchers.get_mut(&context).ok_or(CommandError::NotConnected)?;
+        let (shared, _, source) = attach_shared_watcher(&connection, context_watchers, &key, subscription_id, &context, state.watch_cache.as_ref(), &list_settings);
+        let caches = vec![SubscriptionCache { context: context.clone(), cache: shared.cache.clone(), filter: source.filter.clone() }];
+        (shared.subscribe(), caches, source)
     };
 
-    let mut state = state.lock().await;
+    let (query_tx, query_rx) = tokio::sync::watch::channel(query);
+    let mut task_map = state.task_map.lock().await;
+    let handle = spawn_subscription(app, subscription_id, run_window(rx, caches, query_rx, channel));
+
+    task_map.insert(subscription_id, TaskHandle {
+        handle,
+        metadata: key.metadata(subscription_id, vec![context]),
+        sources: vec![source],
+        query: Some(query_tx),
+        resync: None,
+        context_bridges: HashMap::new(),
+    });
 
-    // 2. Check or Create Source Task
-    let is_new = !state.watchers.contains_key(&key);
-    
-    if is_new {
-        let (tx, _rx) = tokio::sync::broadcast::channel(100);
-        let cache = Arc::new(RwLock::new(HashMap::new()));
-
-        let ar = kube::discovery::ApiResource {
-            group: group.clone(),
-            api_version: api_version.clone(),
-            plural: resource_plural.clone(),
-            version: "".to_string(),
-            kind: "".to_string(),
-        };
+    Ok(subscription_id)
+}
 
-        // Determine which APIs to watch
-        let apis: Vec<Api<DynamicObject>> = if let Some(ns_list) = &sorted_namespaces {
-            // Watch multiple specific namespaces
-            if ns_list.is_empty() {
-                vec![Api::all_with(state.kube_client.clone(), &ar)]
-            } else {
-                ns_list.iter()
-                    .map(|ns| Api::namespaced_with(state.kube_client.clone(), ns, &ar))
-                    .collect()
-            }
-        } else if let Some(ns) = &namespace {
-            // Watch single namespace
-            vec![Api::namespaced_with(state.kube_client.clone(), ns, &ar)]
-        } else {
-            // Watch all
-            vec![Api::all_with(state.kube_client.clone(), &ar)]
-        };
+/// Moves the window of a subscription started with `start_listening_window`, e.g. when scrolling or re-sorting
+#[tauri::command]
+async fn set_window_query(state: CommandGlobalState<'_>, subscription_id: i32, query: ListQuery) -> Result<(), CommandError> {
+    let query = query.compile().map_err(|message| CommandError::InvalidQuery { message })?;
+    let task_map = state.task_map.lock().await;
+    let sender = task_map.get(&subscription_id)
+        .and_then(|task| task.query.as_ref())
+        .ok_or(CommandError::SubscriptionNotFound { id: subscription_id })?;
+    let _ = sender.send(query);
+    Ok(())
+}
 
-        let mut wc = watcher::Config::default().streaming_lists();
-
-        let tx_clone = tx.clone();
-        let cache_clone = cache.clone();
-        let name_clone = name.clone();
-        
-        // Spawn Source Task
-        let source_handle = tokio::task::spawn(async move {
-             match name_clone {
-                Some(name) => {
-                    // Single resource watch (always single API - name implies specific namespace usually or cluster scoped)
-                    if let Some(first_api) = apis.first() {
-                        let mut events = watch_object(first_api.clone(), &name).default_backoff().boxed();
-                        loop {
-                            match events.try_next().await {
-                                Ok(Some(Some(event))) => {
-                                    let listen_event = ResourceListenEvent::Apply {
-                                        resource: serde_json::to_value(&event).unwrap(), // Clone event?
-                                    };
-                                    let _ = tx_clone.send(listen_event);
-                                    
-                                    // Update Cache
-                                    let uid = event.metadata.uid.clone().unwrap_or_default();
-                                    if let Ok(mut c) = cache_clone.write() {
-                                        if let Ok(val) = serde_json::to_value(event) {
-                                             c.insert(uid, val);
-                                        }
-                                    }
-                                },
-                                 Ok(Some(None)) => {
-                                    let listen_event = ResourceListenEvent::SingleResourceNotFoundOrDeleted;
-                                    let _ = tx_clone.send(listen_event);
-                                    if let Ok(mut c) = cache_clone.write() {
-                                        c.clear();
-                                    }
-                                },
-                                Ok(None) => {
-                                    let _ = tx_clone.send(ResourceListenEvent::Error {
-                                        message: "none value given from watcher".to_string()
-                                    });
-                                }
-                                Err(e) => {
-                                    let _ = tx_clone.send(ResourceListenEvent::Error {
-                                        message: e.to_string()
-                                    });
-                                }
-                            }
-                        }
-                    }
-                },
-                None => {
-                    // List/Watch
-                    // Merge streams if multiple APIs
-                    let streams = apis.into_iter().map(|api| {
-                         watcher(api, wc.clone()).default_backoff().boxed()
-                    });
-                    
-                    let mut events = futures_util::stream::select_all(streams);
+/// Sends a subscription's list again from the cache, for a client that lost track of it,
+/// e.g. a patch that didn't apply to its copy of an object
+#[tauri::command]
+async fn resync_subscription(state: CommandGlobalState<'_>, subscription_id: i32) -> Result<(), CommandError> {
+    let task_map = state.task_map.lock().await;
+    let sender = task_map.get(&subscription_id)
+        .and_then(|task| task.resync.as_ref())
+        .ok_or(CommandError::SubscriptionNotFound { id: subscription_id })?;
+    let _ = sender.send(());
+    Ok(())
+}
 
-                    loop {
-                        match events.next().await {
-                            Some(Ok(p)) => {
-                                let listen_event = ResourceListenEvent::from(p.clone());
-                                let _ = tx_clone.send(listen_event);
-                                
-                                // Maintain Cache
-                                if let Ok(mut c) = cache_clone.write() {
-                                    match p {
-                                        Event::Apply(obj) => {
-                                            if let Some(uid) = &obj.metadata.uid {
-                                                if let Ok(val) = serde_json::to_value(&obj) {
-                                                    c.insert(uid.clone(), val);
-                                                }
-                                            }
-                                        },
-                                        Event::Delete(obj) => {
-                                            if let Some(uid) = &obj.metadata.uid {
-                                                c.remove(uid);
-                                            }
-                                        },
-                                        Event::InitApply(obj) => {
-                                            if let Some(uid) = &obj.metadata.uid {
-                                                if let Ok(val) = serde_json::to_value(&obj) {
-                                                    c.insert(uid.clone(), val);
-                                                }
-                                            }
-                                        },
-                                        Event::Init => {
-                                           // Don't clear for multi-watcher due to race conditions
-                                           // c.clear();
-                                        }
-                                        // InitDone nothing
-                                        _ => {}
-                                    }
-                                }
-                            }
-                            Some(Err(e)) => {
-                                let _ = tx_clone.send(ResourceListenEvent::Error {
-                                    message: e.to_string()
-                                });
-                            },
-                            None => {
-                                // All streams finished?
-                                let _ = tx_clone.send(ResourceListenEvent::Error {
-                                    message: "all watcher streams ended".to_string()
-                                });
-                                break;
-                            }
-                        }
-                    }
+/// The objects (context, uid) that pass a subscription's and a query's filters
+fn matching_objects(query: &CompiledQuery, caches: &[SubscriptionCache]) -> HashSet<(String, String)> {
+    let mut matching = HashSet::new();
+    for c in caches {
+        let Ok(guard) = c.cache.read() else { continue };
+        for resource in guard.values() {
+            if c.filter.as_deref().is_none_or(|f| f.matches(resource)) && query.matches(resource) {
+                if let Some(uid) = resource["metadata"]["uid"].as_str() {
+                    matching.insert((c.context.clone(), uid.to_string()));
                 }
             }
-        });
-
-        eprintln!("[{}] Started NEW source task for key {:?}", subscription_id, key);
-        
-        state.watchers.insert(key.clone(), SharedWatcher {
-            tx,
-            cache,
-            source_task: source_handle,
-            ref_count: 0
-        });
-    } else {
-        eprintln!("[{}] Reusing existing source task for key {:?}", subscription_id, key);
+        }
     }
+    matching
+}
 
-    // 3. Increment Ref Count & attach
-    let shared = state.watchers.get_mut(&key).unwrap();
-    shared.ref_count += 1;
-    let mut rx = shared.tx.subscribe();
-    let cache_access = shared.cache.clone();
-
-    // 4. Spawn Bridge Task
-    let bridge_handle = tokio::task::spawn(async move {
-        // Only perform artificial replay if we are joining an EXISTING stream.
-        // If it's NEW, the source task will naturally emit Init/InitDone to the channel.
-        if !is_new {
-            // A. Send Init
-            let _ = channel.send(ResourceListenEvent::Init);
-            
-            // B. Replay Cache
-            // Scope the lock
-            {
-                if let Ok(cache) = cache_access.read() {
-                    for (_, val) in cache.iter() {
-                        let _ = channel.send(ResourceListenEvent::InitApply {
-                            resource: val.clone()
-                        });
-                    }
-                }
-            } // lock released
+/// The cache and uid of the one object an event changes, None for events that can change all of them
+fn changed_object<'a>(event: &'a ResourceListenEvent, caches: &'a [SubscriptionCache], context: Option<&str>) -> Option<(&'a SubscriptionCache, &'a str)> {
+    let uid = match event {
+        ResourceListenEvent::FromContext { context, event } => return changed_object(event, caches, Some(context)),
+        ResourceListenEvent::InitApply { resource }
+        | ResourceListenEvent::Apply { resource }
+        | ResourceListenEvent::Delete { resource } => resource["metadata"]["uid"].as_str()?,
+        ResourceListenEvent::Patch { uid, .. } => uid.as_str(),
+        _ => return None,
+    };
+    let cache = match context {
+        Some(context) => caches.iter().find(|c| c.context == context)?,
+        None => caches.first()?,
+    };
+    Some((cache, uid))
+}
+
+/// Updates `matching` after a change to one object, and tells whether the window may have changed:
+/// only objects that match the filters now or did before can move it
+fn update_matching(query: &CompiledQuery, cache: &SubscriptionCache, uid: &str, matching: &mut HashSet<(String, String)>) -> bool {
+    let matches_now = cache.cache.read().ok()
+        .and_then(|guard| guard.get(uid).map(|resource| {
+            cache.filter.as_deref().is_none_or(|f| f.matches(resource)) && query.matches(resource)
+        }))
+        .unwrap_or(false);
+    let key = (cache.context.clone(), uid.to_string());
+    let matched = if matches_now { !matching.insert(key) } else { matching.remove(&key) };
+    matches_now || matched
+}
 
-            // C. Send InitDone
-            let _ = channel.send(ResourceListenEvent::InitDone);
+/// Recomputes the window whenever the query changes, or the watcher changes an object that passes the filter
+/// (or did), and sends it if it's different
+async fn run_window(
+    mut rx: tokio::sync::broadcast::Receiver<ResourceListenEvent>,
+    caches: Vec<SubscriptionCache>,
+    mut query_rx: tokio::sync::watch::Receiver<CompiledQuery>,
+    channel: Channel<QueryWindow>,
+) {
+    let mut last: Option<QueryWindow> = None;
+    let mut query = query_rx.borrow_and_update().clone();
+    let mut matching = HashSet::new();
+    let mut recompute = true;
+    loop {
+        if recompute {
+            matching = matching_objects(&query, &caches);
+            let window = query_caches(&query, &caches);
+            if last.as_ref() != Some(&window) {
+                if channel.send(window.clone()).is_err() {
+                    break;
+                }
+                last = Some(window);
+            }
         }
 
-        // D. Loop Broadcast
-        loop {
-            match rx.recv().await {
-                Ok(msg) => {
-                    // Send to Tauri channel
-                    if let Err(_) = channel.send(msg) {
-                        // Channel closed by frontend
-                        break;
+        tokio::select! {
+            msg = rx.recv() => {
+                // Lagging only means we don't know which objects changed, the window is always computed from the cache
+                let mut lagged = false;
+                let mut events = Vec::new();
+                match msg {
+                    Ok(event) => events.push(event),
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => lagged = true,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+                // Let a burst of changes settle before sorting everything again
+                tokio::time::sleep(WINDOW_DEBOUNCE).await;
+                loop {
+                    match rx.try_recv() {
+                        Ok(event) => events.push(event),
+                        Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_)) => lagged = true,
+                        Err(_) => break,
                     }
-                },
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                     eprintln!("Bridge task lagged by {} messages", n);
-                     let _ = channel.send(ResourceListenEvent::Init);
-                },
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                }
+                recompute = lagged || events.iter().any(|event| match changed_object(event, &caches, None) {
+                    Some((cache, uid)) => update_matching(&query, cache, uid, &mut matching),
+                    None => true,
+                });
+            }
+            changed = query_rx.changed() => {
+                if changed.is_err() {
                     break;
                 }
+                query = query_rx.borrow_and_update().clone();
+                recompute = true;
             }
         }
-    });
-
-    // 5. Register Bridge Task in task_map
-    let metadata = TaskMetadata {
-        id: subscription_id,
-        group,
-        api_version,
-        resource_plural,
-        name,
-        namespace,
-        namespaces: sorted_namespaces,
-    };
-    state.task_map.insert(subscription_id, TaskHandle {
-        handle: bridge_handle,
-        metadata
-    });
-
-    Ok(subscription_id)
+    }
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -558,9 +1932,14 @@ impl XApiGroup {
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 struct WatcherDebugInfo {
+    context: String,
     key: String, // Simplified string representation
     ref_count: usize,
     cache_size: usize,
+    /// Estimated memory taken by the cached objects
+    memory_bytes: usize,
+    /// How long the watcher has been unused, if it is
+    idle_for_ms: Option<u64>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -569,58 +1948,59 @@ struct DebugInfo {
     open_tasks: i32,
     tasks: Vec<TaskMetadata>,
     watchers: Vec<WatcherDebugInfo>,
+    /// How each connected cluster lists, unset until a watcher found out
+    list_strategies: HashMap<String, Option<ListStrategy>>,
 }
 
 #[tauri::command]
 async fn debug(state: CommandGlobalState<'_>) -> Result<DebugInfo, ()> {
-    let state = state.lock().await;
-
-    let tasks: Vec<TaskMetadata> = state.task_map.values().map(|t| t.metadata.clone()).collect();
+    let tasks: Vec<TaskMetadata> = state.task_map.lock().await.values().map(|t| t.metadata.clone()).collect();
     
-    let watchers: Vec<WatcherDebugInfo> = state.watchers.iter().map(|(k, v)| {
-        let cache_len = if let Ok(c) = v.cache.read() {
-            c.len()
-        } else {
-            0
-        };
-        WatcherDebugInfo {
-            key: format!("{}/{}/{}", k.group, k.api_version, k.resource_plural),
-            ref_count: v.ref_count,
-            cache_size: cache_len
-        }
+    let watchers: Vec<WatcherDebugInfo> = state.watchers.lock().await.iter().flat_map(|(context, context_watchers)| {
+        context_watchers.watchers.iter().map(move |(k, v)| {
+            let (cache_len, memory_bytes) = if let Ok(c) = v.cache.read() {
+                (c.len(), c.bytes())
+            } else {
+                (0, 0)
+            };
+            WatcherDebugInfo {
+                context: context.clone(),
+                key: format!("{}/{}/{}", k.group, k.api_version, k.resource_plural),
+                ref_count: v.ref_count,
+                cache_size: cache_len,
+                memory_bytes,
+                idle_for_ms: v.idle_since.map(|since| since.elapsed().as_millis() as u64),
+            }
+        })
     }).collect();
 
+    let list_strategies = state.connections.read().await.iter()
+        .map(|(context, connection)| (context.clone(), connection.list_strategy.get()))
+        .collect();
+
     Ok(DebugInfo {
-        open_tasks: state.task_map.len() as i32,
+        open_tasks: tasks.len() as i32,
         tasks,
-        watchers
+        watchers,
+        list_strategies,
     })
 }
 
 #[tauri::command]
-async fn list_api_resources(state: CommandGlobalState<'_>) -> Result<Vec<XApiGroup>, String> {
-    let mut state = state.lock().await;
-
-    // Lazily initialize discovery if needed
-    if state.kube_discovery.is_none() {
-        state.kube_discovery = Some(Discovery::new(state.kube_client.clone()));
-    }
-
-    // Move the Discovery out, run it, and put it back to avoid moving out of the MutexGuard field
-    if let Some(mut discovery) = state.kube_discovery.take() {
-        // If it has no groups yet, perform the run to populate cache
-        let needs_run = discovery.groups().next().is_none();
-        if needs_run {
-            discovery = discovery.run().await.map_err(|e| e.to_string())?;
-        }
-        state.kube_discovery = Some(discovery);
+async fn list_api_resources(state: CommandGlobalState<'_>, context: Option<String>) -> Result<Vec<XApiGroup>, CommandError> {
+    let connection = state.connection(context).await?;
+
+    // Only this context's discovery is locked while it runs:
+    // other commands go ahead, and concurrent calls wait for this run instead of repeating it
+    let mut discovery = connection.discovery.lock().await;
+    if discovery.is_none() {
+        let run = Discovery::new(connection.client.clone()).run().await;
+        *discovery = Some(run.map_err(|e| proxy::request_error(connection.proxy_url.as_ref(), e))?);
     }
 
-    let groups = state
-        .kube_discovery
-        .as_ref()
-        .unwrap()
-        .groups()
+    let groups = discovery
+        .iter()
+        .flat_map(|discovery| discovery.groups())
         .map(XApiGroup::from_api_group)
         .collect();
 
@@ -628,10 +2008,9 @@ async fn list_api_resources(state: CommandGlobalState<'_>) -> Result<Vec<XApiGro
 }
 
 #[tauri::command]
-async fn exec_raw(state: CommandGlobalState<'_>, path: String) -> Result<String, String> {
-
-    // Create a Kubernetes client
-    let client = Client::try_default().await.map_err(|e| e.to_string())?;
+async fn exec_raw(state: CommandGlobalState<'_>, path: String, context: Option<String>) -> Result<String, CommandError> {
+    let connection = state.connection(context).await?;
+    let (client, proxy_url) = (connection.client.clone(), connection.proxy_url.clone());
 
     let response = client.request_text(
         Request::builder()
@@ -639,11 +2018,12 @@ async fn exec_raw(state: CommandGlobalState<'_>, path: String) -> Result<String,
             .body("".into())
             .unwrap(),
     );
-    let data = response.await.map_err(|e| e.to_string())?;
+    let data = response.await.map_err(|e| proxy::request_error(proxy_url.as_ref(), e))?;
     Ok(data)
 }
 
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 struct SubscriptionKey {
@@ -653,61 +2033,326 @@ struct SubscriptionKey {
     namespace: Option<String>,
     name: Option<String>,
     namespaces: Option<Vec<String>>,
+    /// Passed to the API server, e.g. `app=checkout,tier!=cache`
+    label_selector: Option<String>,
+    /// Passed to the API server, e.g. `status.phase!=Running`
+    field_selector: Option<String>,
+    /// Only stream apiVersion, kind and metadata (see [metadata_event_to_dynamic])
+    metadata_only: bool,
+    /// Stream server-side printed Table rows instead of objects (see [table::run_table_watch])
+    as_table: bool,
+}
+
+impl SubscriptionKey {
+    /// The key for a subscription's arguments, made the same for equivalent subscriptions so they share a watcher
+    fn new(
+        group: String,
+        api_version: String,
+        resource_plural: String,
+        name: Option<String>,
+        namespace: Option<String>,
+        namespaces: Option<Vec<String>>,
+        label_selector: Option<String>,
+        field_selector: Option<String>,
+        metadata_only: Option<bool>,
+        as_table: Option<bool>,
+    ) -> SubscriptionKey {
+        let namespaces = namespaces.map(|mut ns_list| {
+            ns_list.sort();
+            ns_list
+        });
+        // Single object watches always get the full object
+        let as_table = as_table.unwrap_or(false) && name.is_none();
+        let metadata_only = metadata_only.unwrap_or(false) && name.is_none() && !as_table;
+        SubscriptionKey {
+            group,
+            api_version,
+            resource_plural,
+            namespace,
+            name,
+            namespaces,
+            label_selector: normalize_selector(label_selector),
+            field_selector: normalize_selector(field_selector),
+            metadata_only,
+            as_table,
+        }
+    }
+
+    /// What `debug` shows of a subscription with this key, reading from `contexts`
+    fn metadata(&self, id: i32, contexts: Vec<String>) -> TaskMetadata {
+        TaskMetadata {
+            id,
+            contexts,
+            group: self.group.clone(),
+            api_version: self.api_version.clone(),
+            resource_plural: self.resource_plural.clone(),
+            name: self.name.clone(),
+            namespace: self.namespace.clone(),
+            namespaces: self.namespaces.clone(),
+            label_selector: self.label_selector.clone(),
+            field_selector: self.field_selector.clone(),
+            metadata_only: self.metadata_only,
+            as_table: self.as_table,
+        }
+    }
+
+    /// Whether the watcher's list may be saved to disk: full objects from a single API,
+    /// and never secrets
+    fn persistable(&self) -> bool {
+        self.name.is_none()
+            && !self.metadata_only
+            && !self.as_table
+            && self.namespaces.as_ref().is_none_or(|ns| ns.len() <= 1)
+            && watch_cache::persistable(&self.group, &self.resource_plural)
+    }
+
+    /// Identifies the list in the on-disk cache
+    fn cache_id(&self, context: &str) -> String {
+        format!("{}/{:?}", context, self)
+    }
+}
+
+/// Trims a selector so that equivalent subscriptions share a watcher, treating an empty selector as none
+fn normalize_selector(selector: Option<String>) -> Option<String> {
+    selector
+        .map(|s| s.split(',').map(str::trim).filter(|part| !part.is_empty()).collect::<Vec<_>>().join(","))
+        .filter(|s| !s.is_empty())
 }
 
 struct SharedWatcher {
     // To send control signals or just purely broadcast events
     tx: tokio::sync::broadcast::Sender<ResourceListenEvent>,
 
+    // Subscribed before the source task started, for the first bridge so it misses nothing
+    first_receiver: Option<tokio::sync::broadcast::Receiver<ResourceListenEvent>>,
+
     // Latest state for "Replay" to new subscribers
     // stored as JSON values for simplicity since we broadcast JSON
-    cache: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    cache: Arc<RwLock<ObjectCache>>,
+
+    // Newest resourceVersion in the cache, to resume from when persisted
+    resource_version: Arc<RwLock<Option<String>>>,
+
+    // Set while the cache holds a list loaded from disk that hasn't caught up yet
+    stale: Arc<AtomicBool>,
 
-    // Handle to the Source Task (to abort it when ref_count=0)
+    // Column definitions of a Table subscription
+    table_columns: Arc<RwLock<Option<serde_json::Value>>>,
+
+    // Latest health of the source task
+    state: Arc<RwLock<WatchState>>,
+
+    // Handle to the Source Task (to abort it once unused for long enough)
     source_task: TokioJoinHandle<()>,
 
     // Number of active Bridge Tasks using this source
     ref_count: usize,
+
+    // When ref_count dropped to 0, unset while it's in use
+    idle_since: Option<Instant>,
 }
 
-struct GlobalState {
-    kubeconfig: Option<Kubeconfig>,
-    kube_client: Client,
-    kube_discovery: Option<Discovery>,
-    task_map: HashMap<i32, TaskHandle>,
+/// What a bridge reads from its shared watcher besides the broadcast, to replay it
+#[derive(Clone)]
+struct WatcherView {
+    /// To subscribe again along with a snapshot of the cache
+    tx: tokio::sync::broadcast::Sender<ResourceListenEvent>,
+    cache: Arc<RwLock<ObjectCache>>,
+    stale: Arc<AtomicBool>,
+    table_columns: Arc<RwLock<Option<serde_json::Value>>>,
+    state: Arc<RwLock<WatchState>>,
+    /// Set when the bridge only wants part of the watcher's list
+    filter: Option<Arc<SubsetFilter>>,
+}
+
+impl SharedWatcher {
+    /// A receiver for a new bridge
+    fn subscribe(&mut self) -> tokio::sync::broadcast::Receiver<ResourceListenEvent> {
+        self.first_receiver.take().unwrap_or_else(|| self.tx.subscribe())
+    }
+
+    /// Restarts the source task with a rebuilt connection, e.g. after the credentials changed.
+    /// The broadcast and the cache stay, so bridges carry on and see a relist.
+    fn restart(&mut self, connection: &ClusterConnection, key: &SubscriptionKey, settings: &ListSettings) {
+        TokioJoinHandle::abort(&self.source_task);
+        // Whatever the old credentials ran into doesn't hold for the new ones
+        HealthReporter::new(self.tx.clone(), self.state.clone(), None).set(WatchState::Relisting);
+        self.source_task = spawn_source_task(connection, key, self.view(None), self.resource_version.clone(), None, settings);
+    }
+
+    fn view(&self, filter: Option<Arc<SubsetFilter>>) -> WatcherView {
+        WatcherView {
+            tx: self.tx.clone(),
+            cache: self.cache.clone(),
+            stale: self.stale.clone(),
+            table_columns: self.table_columns.clone(),
+            state: self.state.clone(),
+            filter,
+        }
+    }
+}
+
+/// The client of a single kube context. Replaced as a whole when the credentials change.
+struct ClusterConnection {
+    client: Client,
+    /// The proxy requests go through (without credentials), if any
+    proxy_url: Option<String>,
+    /// The API groups of the cluster, once `list_api_resources` discovered them.
+    /// Locked on its own, discovery takes a request per group.
+    discovery: Mutex<Option<Discovery>>,
+    /// Whether the cluster supports streaming lists, once a watcher found out.
+    /// Kept when the client is rebuilt, the API server is still the same.
+    list_strategy: ClusterListStrategy,
+}
+
+impl ClusterConnection {
+    fn new(client: Client, proxy_url: Option<String>) -> Self {
+        ClusterConnection {
+            client,
+            proxy_url,
+            discovery: Mutex::new(None),
+            list_strategy: ClusterListStrategy::default(),
+        }
+    }
+}
+
+/// The shared watchers of a single kube context
+#[derive(Default)]
+struct ContextWatchers {
     watchers: HashMap<SubscriptionKey, SharedWatcher>,
+    /// Object count of lists watched before and when their watcher stopped, by key (see [known_list_size]).
+    /// At most [MAX_LIST_SIZES], and forgotten with the rest on disconnect.
+    list_sizes: HashMap<SubscriptionKey, (usize, Instant)>,
+}
+
+/// State shared by every command.
+/// Each part is locked on its own and never across a request to a cluster (except a connection's discovery),
+/// so a slow cluster doesn't hold up the rest of the app.
+/// A command that needs several parts at once locks them in the order they are declared.
+struct GlobalState {
+    kubeconfig: async_runtime::RwLock<Option<Kubeconfig>>,
+    kubeconfig_watch: Mutex<Option<KubeconfigWatch>>,
+    /// The context used by commands that don't specify one (the last one passed to `start`)
+    current_context: async_runtime::RwLock<Option<String>>,
+    proxy_settings: async_runtime::RwLock<ProxySettings>,
+    list_settings: async_runtime::RwLock<ListSettings>,
+    /// Connections by context name, opened with `start`
+    connections: async_runtime::RwLock<HashMap<String, Arc<ClusterConnection>>>,
+    task_map: Mutex<HashMap<i32, TaskHandle>>,
+    /// Watcher registry, by context name. Has an entry for every connected context
+    watchers: Mutex<HashMap<String, ContextWatchers>>,
+    /// The ID of the last subscription, IDs are never reused
+    next_task_id: AtomicI32,
+    /// Where watched lists are saved between runs, if there is an app cache directory
+    watch_cache: Option<WatchCacheStore>,
+    /// Recent `/version` answers of the clusters in the kubeconfig, see [flavour::describe_contexts]
+    server_versions: ServerVersions,
+    /// Recent kubeconfigs of script sources, see [kubeconfig::discover]
+    script_outputs: ScriptOutputs,
 }
 
-type CommandGlobalState<'a> = State<'a, Mutex<GlobalState>>;
+impl GlobalState {
+    fn allocate_task_id(&self) -> i32 {
+        self.next_task_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Resolves the context a command should run against
+    async fn context_name(&self, context: Option<String>) -> Result<String, CommandError> {
+        match context {
+            Some(context) => Ok(context),
+            None => self.current_context.read().await.clone().ok_or(CommandError::NotConnected),
+        }
+    }
+
+    async fn connection(&self, context: Option<String>) -> Result<Arc<ClusterConnection>, CommandError> {
+        let context = self.context_name(context).await?;
+        self.connections.read().await.get(&context).cloned().ok_or(CommandError::NotConnected)
+    }
+
+    /// Opens a connection to `context_name` if there isn't one already
+    async fn connect(&self, context_name: &str) -> Result<Arc<ClusterConnection>, CommandError> {
+        if let Some(connection) = self.connections.read().await.get(context_name) {
+            return Ok(connection.clone());
+        }
+        let kubeconfig = self.kubeconfig.read().await.clone().ok_or(CommandError::KubeconfigNotLoaded)?;
+        let proxy_settings = self.proxy_settings.read().await.clone();
+        let connection = build_client(kubeconfig, context_name, &proxy_settings).await?;
+
+        // Another command may have connected meanwhile, keep the connection it made
+        let mut connections = self.connections.write().await;
+        let connection = connections.entry(context_name.to_string())
+            .or_insert_with(|| Arc::new(connection))
+            .clone();
+        self.watchers.lock().await.entry(context_name.to_string()).or_default();
+        Ok(connection)
+    }
+}
+
+type CommandGlobalState<'a> = State<'a, GlobalState>;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            async_runtime::block_on(async {
-                let client = Client::try_default().await.unwrap();
-
-                app.manage(Mutex::new(GlobalState {
-                    kube_client: client.clone(),
-                    kube_discovery: Some(Discovery::new(client)),
-                    task_map: HashMap::new(),
-                    watchers: HashMap::new(),
-                    kubeconfig: None
-                }));
+            app.manage(GlobalState {
+                kubeconfig: async_runtime::RwLock::new(None),
+                kubeconfig_watch: Mutex::new(None),
+                current_context: async_runtime::RwLock::new(None),
+                proxy_settings: async_runtime::RwLock::new(ProxySettings::load(app.handle())),
+                list_settings: async_runtime::RwLock::new(ListSettings::load(app.handle())),
+                connections: async_runtime::RwLock::new(HashMap::new()),
+                task_map: Mutex::new(HashMap::new()),
+                watchers: Mutex::new(HashMap::new()),
+                next_task_id: AtomicI32::new(0),
+                watch_cache: WatchCacheStore::new(app.handle()),
+                server_versions: ServerVersions::default(),
+                script_outputs: ScriptOutputs::default(),
             });
+            async_runtime::spawn(reap_idle_watchers(app.handle().clone()));
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            // Channels of the previous page are dead, so are the subscriptions using them
+            if payload.event() == PageLoadEvent::Started {
+                let app = webview.app_handle().clone();
+                // Taken before the new page's scripts run, everything it subscribes to comes after
+                let last_id = app.state::<GlobalState>().next_task_id.load(Ordering::Relaxed);
+                async_runtime::spawn(async move {
+                    release_all_tasks(&app, last_id).await;
+                });
+            }
+        })
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             list_api_resources,
             exec_raw,
             start_listening,
+            start_listening_composed,
+            start_listening_window,
+            set_window_query,
+            query_resources,
+            resync_subscription,
             stop_listen_task,
             detail_resource,
             list_kube_contexts,
+            get_kubeconfig_sources,
+            set_kubeconfig_sources,
+            watch_kube_contexts,
+            get_proxy_settings,
+            set_proxy_settings,
+            get_list_settings,
+            set_list_settings,
             start,
+            probe_context,
+            disconnect,
             debug
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                persist_all_watchers(app);
+            }
+        });
 }
@@ -0,0 +1,158 @@
+use crate::settings;
+use kube::runtime::watcher::{self, ListSemantic};
+use kube::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use tauri::AppHandle;
+
+const LIST_SETTINGS_FILE: &str = "lists.json";
+
+/// Same default as client-go and kube
+const DEFAULT_PAGE_SIZE: u32 = 500;
+
+const DEFAULT_LINGER_SECS: u64 = 60;
+const DEFAULT_IDLE_BUDGET_MB: u64 = 256;
+
+/// The first minor release whose API server knows about `sendInitialEvents`, behind the WatchList feature gate.
+/// Older servers ignore the parameter, and the initial list would never arrive.
+const WATCH_LIST_MINOR: u32 = 27;
+
+/// How a cluster's watchers fetch the initial list
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ListStrategy {
+    /// A single watch that streams the existing objects first, needs the WatchList feature gate
+    Streaming,
+    /// Paginated lists, served from the API server's cache where possible, then a watch
+    Paginated,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListSettings {
+    /// Objects per page when a cluster doesn't support streaming lists
+    #[serde(default = "default_page_size")]
+    pub(crate) page_size: u32,
+    /// How long a watcher nobody subscribes to keeps watching, so coming back to its list is instant.
+    /// 0 stops watchers as soon as they are unused.
+    #[serde(default = "default_linger_secs")]
+    pub(crate) linger_secs: u64,
+    /// How much memory the caches of unused watchers may take together, the least recently used go first
+    #[serde(default = "default_idle_budget_mb")]
+    pub(crate) idle_budget_mb: u64,
+    /// Drop `metadata.managedFields` from watched objects, applies to watchers started afterwards
+    #[serde(default)]
+    pub(crate) strip_managed_fields: bool,
+}
+
+fn default_page_size() -> u32 {
+    DEFAULT_PAGE_SIZE
+}
+
+fn default_linger_secs() -> u64 {
+    DEFAULT_LINGER_SECS
+}
+
+fn default_idle_budget_mb() -> u64 {
+    DEFAULT_IDLE_BUDGET_MB
+}
+
+impl Default for ListSettings {
+    fn default() -> Self {
+        ListSettings {
+            page_size: DEFAULT_PAGE_SIZE,
+            linger_secs: DEFAULT_LINGER_SECS,
+            idle_budget_mb: DEFAULT_IDLE_BUDGET_MB,
+            strip_managed_fields: false,
+        }
+    }
+}
+
+impl ListSettings {
+    pub(crate) fn linger(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.linger_secs)
+    }
+
+    pub(crate) fn idle_budget_bytes(&self) -> usize {
+        (self.idle_budget_mb as usize).saturating_mul(1024 * 1024)
+    }
+
+    pub(crate) fn load(app: &AppHandle) -> ListSettings {
+        settings::load(app, LIST_SETTINGS_FILE)
+    }
+
+    pub(crate) fn save(&self, app: &AppHandle) -> Result<(), String> {
+        settings::save(app, LIST_SETTINGS_FILE, self)
+    }
+}
+
+/// The strategy of one cluster, shared by its watchers so only the first one has to find out
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ClusterListStrategy(Arc<RwLock<Option<ListStrategy>>>);
+
+impl ClusterListStrategy {
+    pub(crate) fn get(&self) -> Option<ListStrategy> {
+        self.0.read().ok().and_then(|s| *s)
+    }
+
+    fn set(&self, strategy: ListStrategy) {
+        if let Ok(mut s) = self.0.write() {
+            *s = Some(strategy);
+        }
+    }
+
+    /// The strategy to try, deciding from `/version` the first time.
+    /// A server recent enough to know streaming lists may still have them disabled,
+    /// which only the first streaming list tells (see [streaming_unsupported]).
+    pub(crate) async fn resolve(&self, client: &Client) -> ListStrategy {
+        if let Some(strategy) = self.get() {
+            return strategy;
+        }
+        match client.apiserver_version().await {
+            Ok(info) if minor_version(&info.minor).is_some_and(|minor| minor < WATCH_LIST_MINOR) => {
+                eprintln!("API server {} predates streaming lists, using paginated lists", info.git_version);
+                self.set(ListStrategy::Paginated);
+                ListStrategy::Paginated
+            }
+            _ => ListStrategy::Streaming,
+        }
+    }
+
+    /// A streaming list completed, so the server supports them
+    pub(crate) fn confirm_streaming(&self) {
+        if self.get().is_none() {
+            self.set(ListStrategy::Streaming);
+        }
+    }
+
+    pub(crate) fn fall_back(&self) {
+        self.set(ListStrategy::Paginated);
+    }
+}
+
+/// The minor version from `/version`, which some providers suffix, e.g. `29+` on EKS and GKE
+fn minor_version(minor: &str) -> Option<u32> {
+    let digits: String = minor.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// `wc` set up to list with `strategy`
+pub(crate) fn configure(wc: watcher::Config, strategy: ListStrategy, page_size: u32) -> watcher::Config {
+    match strategy {
+        ListStrategy::Streaming => wc.streaming_lists(),
+        ListStrategy::Paginated => wc.list_semantic(ListSemantic::Any).page_size(page_size),
+    }
+}
+
+/// Whether a failed streaming list means the API server doesn't support them.
+/// With the WatchList feature gate off, `sendInitialEvents` fails validation (422), some proxies answer 400.
+pub(crate) fn streaming_unsupported(e: &watcher::Error) -> bool {
+    let (code, message) = match e {
+        watcher::Error::WatchStartFailed(kube::Error::Api(response))
+        | watcher::Error::WatchFailed(kube::Error::Api(response)) => (response.code, &response.message),
+        watcher::Error::WatchError(response) => (response.code, &response.message),
+        _ => return false,
+    };
+    matches!(code, 400 | 422)
+        && ["sendInitialEvents", "WatchList", "resourceVersionMatch"].iter().any(|hint| message.contains(hint))
+}
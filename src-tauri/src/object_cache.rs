@@ -0,0 +1,102 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An object as kept in a watcher's cache and sent to its bridges.
+/// The cache, the broadcast and every replay share one allocation per version of the object.
+pub(crate) type SharedObject = Arc<Value>;
+
+/// The objects of a shared watcher by uid, with a running estimate of the memory they take
+#[derive(Debug, Default)]
+pub(crate) struct ObjectCache {
+    /// Each object with its estimated size
+    objects: HashMap<String, (SharedObject, usize)>,
+    bytes: usize,
+    /// Drop `metadata.managedFields` from incoming objects, they are often half of an object and rarely looked at
+    strip_managed_fields: bool,
+}
+
+impl ObjectCache {
+    pub(crate) fn new(strip_managed_fields: bool) -> Self {
+        ObjectCache { strip_managed_fields, ..Default::default() }
+    }
+
+    /// Turns an incoming object into the form that is cached and sent
+    pub(crate) fn ingest(&self, mut object: Value) -> SharedObject {
+        if self.strip_managed_fields {
+            if let Some(metadata) = object.get_mut("metadata").and_then(Value::as_object_mut) {
+                metadata.remove("managedFields");
+            }
+        }
+        Arc::new(object)
+    }
+
+    pub(crate) fn get(&self, uid: &str) -> Option<&SharedObject> {
+        self.objects.get(uid).map(|(object, _)| object)
+    }
+
+    /// Returns the version it replaces, if any
+    pub(crate) fn insert(&mut self, uid: String, object: SharedObject) -> Option<SharedObject> {
+        let size = estimated_size(&object);
+        self.bytes += size;
+        let (previous, previous_size) = self.objects.insert(uid, (object, size))?;
+        self.bytes -= previous_size;
+        Some(previous)
+    }
+
+    pub(crate) fn remove(&mut self, uid: &str) -> Option<SharedObject> {
+        let (object, size) = self.objects.remove(uid)?;
+        self.bytes -= size;
+        Some(object)
+    }
+
+    /// Removes the objects `remove` picks, and returns them
+    pub(crate) fn remove_where(&mut self, remove: impl Fn(&str, &Value) -> bool) -> Vec<SharedObject> {
+        let uids: Vec<String> = self.objects.iter()
+            .filter(|(uid, (object, _))| remove(uid, object))
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        uids.iter().filter_map(|uid| self.remove(uid)).collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.objects.clear();
+        self.bytes = 0;
+    }
+
+    /// Replaces everything with a fresh list of (uid, object), and returns the objects as cached
+    pub(crate) fn replace(&mut self, objects: Vec<(String, Value)>) -> Vec<SharedObject> {
+        self.clear();
+        objects.into_iter()
+            .map(|(uid, object)| {
+                let object = self.ingest(object);
+                self.insert(uid, object.clone());
+                object
+            })
+            .collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &SharedObject> {
+        self.objects.values().map(|(object, _)| object)
+    }
+
+    /// Estimated memory taken by the objects, see [estimated_size]
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+/// Roughly how much memory a JSON value takes: its strings plus a fixed amount per value.
+/// Good enough to compare watchers and enforce budgets, not an exact allocation count.
+fn estimated_size(value: &Value) -> usize {
+    std::mem::size_of::<Value>() + match value {
+        Value::String(s) => s.capacity(),
+        Value::Array(items) => items.iter().map(estimated_size).sum(),
+        Value::Object(fields) => fields.iter().map(|(k, v)| std::mem::size_of::<String>() + k.capacity() + estimated_size(v)).sum(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,149 @@
+use k8s_openapi::api::authentication::v1::SelfSubjectReview;
+use kube::api::PostParams;
+use kube::{Api, Config};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::http::Request;
+
+/// Upper bound for each request made by a probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a context isn't usable
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type", content = "info")]
+pub(crate) enum ProbeFailure {
+    /// The API server's host name doesn't resolve
+    Dns { message: String },
+    /// The TLS handshake failed, e.g. an unknown CA or expired server certificate
+    Tls { message: String },
+    /// The credentials were rejected, or the exec plugin / auth provider failed
+    Auth { message: String },
+    /// The API server didn't answer in time
+    Timeout,
+    /// The proxy couldn't be reached or couldn't reach the API server
+    Proxy { message: String },
+    /// Any other connection failure (refused, reset, ...)
+    Connection { message: String },
+    Other { message: String },
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProbeResult {
+    /// Round trip time of the `/version` request
+    pub(crate) latency_ms: Option<u64>,
+    pub(crate) server_version: Option<String>,
+    /// Whether `/readyz` reported ok, if we were allowed to ask
+    pub(crate) ready: Option<bool>,
+    pub(crate) username: Option<String>,
+    pub(crate) groups: Vec<String>,
+    pub(crate) failure: Option<ProbeFailure>,
+}
+
+/// Sorts a request error into a [ProbeFailure].
+/// Transport errors are boxed several layers deep by hyper and tower,
+/// so we look at the whole source chain.
+fn classify(e: &kube::Error, through_proxy: bool) -> ProbeFailure {
+    match e {
+        kube::Error::Api(response) if response.code == 401 || response.code == 403 => {
+            return ProbeFailure::Auth { message: response.message.clone() };
+        }
+        kube::Error::Auth(_) => return ProbeFailure::Auth { message: e.to_string() },
+        kube::Error::RustlsTls(_) => return ProbeFailure::Tls { message: e.to_string() },
+        kube::Error::HyperError(_) | kube::Error::Service(_) => {}
+        _ => return ProbeFailure::Other { message: e.to_string() },
+    }
+
+    let mut chain = vec![e.to_string()];
+    let mut source = std::error::Error::source(e);
+    while let Some(inner) = source {
+        chain.push(inner.to_string());
+        source = inner.source();
+    }
+    let message = chain.join(": ");
+    let lower = message.to_lowercase();
+
+    if lower.contains("dns error") || lower.contains("failed to lookup address") || lower.contains("name or service not known") {
+        ProbeFailure::Dns { message }
+    } else if lower.contains("certificate") || lower.contains("tls") || lower.contains("handshake") {
+        ProbeFailure::Tls { message }
+    } else if lower.contains("timed out") || lower.contains("deadline has elapsed") {
+        ProbeFailure::Timeout
+    } else if through_proxy {
+        ProbeFailure::Proxy { message }
+    } else {
+        ProbeFailure::Connection { message }
+    }
+}
+
+/// Runs a probe request, or fails with [ProbeFailure::Timeout]
+async fn timed<T>(future: impl std::future::Future<Output = Result<T, kube::Error>>) -> Result<Result<T, kube::Error>, ProbeFailure> {
+    tokio::time::timeout(PROBE_TIMEOUT, future).await.map_err(|_| ProbeFailure::Timeout)
+}
+
+async fn with_timeout<T>(future: impl std::future::Future<Output = Result<T, kube::Error>>, through_proxy: bool) -> Result<T, ProbeFailure> {
+    timed(future).await?.map_err(|e| classify(&e, through_proxy))
+}
+
+/// Checks that a context is usable: the server answers, is ready, and accepts our credentials
+pub(crate) async fn probe(mut config: Config) -> ProbeResult {
+    let mut result = ProbeResult::default();
+    let through_proxy = config.proxy_url.is_some();
+    config.connect_timeout = Some(PROBE_TIMEOUT);
+    config.read_timeout = Some(PROBE_TIMEOUT);
+
+    let client = match crate::proxy::client(config) {
+        Ok(client) => client,
+        Err(e) => {
+            result.failure = Some(ProbeFailure::Other { message: e.to_string() });
+            return result;
+        }
+    };
+
+    // 1. /version, for latency and server version
+    let started = Instant::now();
+    match with_timeout(client.apiserver_version(), through_proxy).await {
+        Ok(info) => {
+            result.latency_ms = Some(started.elapsed().as_millis() as u64);
+            result.server_version = Some(info.git_version);
+        }
+        Err(failure) => {
+            result.failure = Some(failure);
+            return result;
+        }
+    }
+
+    // 2. /readyz, some RBAC setups don't let users read it so a 401/403 isn't a failure.
+    // A server that isn't ready answers with an error status (usually 500), which still leaves it reachable.
+    let readyz = Request::builder().uri("/readyz").body(Vec::new()).unwrap();
+    match timed(client.request_text(readyz)).await {
+        Ok(Ok(body)) => result.ready = Some(body.trim() == "ok"),
+        Ok(Err(kube::Error::Api(response))) if response.code == 401 || response.code == 403 => {}
+        Ok(Err(kube::Error::Api(_))) => result.ready = Some(false),
+        Ok(Err(e)) => {
+            result.failure = Some(classify(&e, through_proxy));
+            return result;
+        }
+        Err(failure) => {
+            result.failure = Some(failure);
+            return result;
+        }
+    }
+
+    // 3. SelfSubjectReview, to find out who we are (and whether the credentials work at all)
+    let reviews: Api<SelfSubjectReview> = Api::all(client);
+    match timed(reviews.create(&PostParams::default(), &SelfSubjectReview::default())).await {
+        Ok(Ok(review)) => {
+            if let Some(user_info) = review.status.and_then(|s| s.user_info) {
+                result.username = user_info.username;
+                result.groups = user_info.groups.unwrap_or_default();
+            }
+        }
+        // Clusters older than 1.28 don't serve SelfSubjectReview
+        Ok(Err(kube::Error::Api(response))) if response.code == 404 => {}
+        Ok(Err(e)) => result.failure = Some(classify(&e, through_proxy)),
+        Err(failure) => result.failure = Some(failure),
+    }
+
+    result
+}
@@ -0,0 +1,227 @@
+use crate::error::CommandError;
+use crate::settings;
+use hyper_timeout::TimeoutConnector;
+use hyper_util::client::legacy::connect::proxy::SocksV5;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use kube::client::{Body, ConfigExt};
+use kube::{Client, Config};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tauri::http::Uri;
+use tower::{BoxError, ServiceBuilder};
+
+const PROXY_SETTINGS_FILE: &str = "proxy.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ProxyProtocol {
+    /// HTTP CONNECT
+    Http,
+    Socks5,
+}
+
+/// A proxy set in the app for a context, replacing the kubeconfig's `proxy-url`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProxyOverride {
+    pub(crate) protocol: ProxyProtocol,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    /// Basic auth for HTTP proxies, username/password authentication for SOCKS5 proxies
+    pub(crate) username: Option<String>,
+    /// Never sent back to the client (see [ProxyOverrideInfo]).
+    /// When setting, unset keeps the saved password of the same user.
+    pub(crate) password: Option<String>,
+}
+
+impl ProxyOverride {
+    fn display_url(&self) -> String {
+        format!("{}://{}:{}", self.scheme(), self.host, self.port)
+    }
+
+    fn scheme(&self) -> &'static str {
+        match self.protocol {
+            ProxyProtocol::Http => "http",
+            ProxyProtocol::Socks5 => "socks5",
+        }
+    }
+
+    /// Why the override can't be used, if it can't
+    fn check(&self) -> Result<(), CommandError> {
+        let invalid = |message: &str| Err(CommandError::Proxy { proxy_url: self.display_url(), message: message.to_string() });
+        let Some(username) = &self.username else { return Ok(()) };
+        let password = self.password.as_deref().unwrap_or_default();
+        if !valid_userinfo(username, false) || !valid_userinfo(password, true) {
+            return invalid("proxy credentials may only contain letters, digits and -._~!$&'()*+,;=%@ (and : in the password)");
+        }
+        // The SOCKS5 handshake has a byte for each length
+        if self.protocol == ProxyProtocol::Socks5 && (username.len() > 255 || password.len() > 255) {
+            return invalid("SOCKS5 usernames and passwords can be at most 255 characters long");
+        }
+        Ok(())
+    }
+
+    fn to_uri(&self) -> Result<Uri, CommandError> {
+        self.check()?;
+        // Both kinds of proxy take credentials from the URL, see [client]
+        let userinfo = match &self.username {
+            Some(username) => format!("{}:{}@", username, self.password.as_deref().unwrap_or_default()),
+            None => String::new(),
+        };
+        let url = format!("{}://{}{}:{}", self.scheme(), userinfo, self.host, self.port);
+        url.parse::<Uri>().map_err(|e| CommandError::Proxy {
+            proxy_url: self.display_url(),
+            message: e.to_string(),
+        })
+    }
+}
+
+/// Whether a username or password can go into the proxy URL as it is.
+/// The HTTP proxy connector builds the Proxy-Authorization header from the URL without percent-decoding it
+/// (and so does [client] for SOCKS5), so credentials can't be encoded,
+/// and only characters a URL allows there unencoded are accepted.
+fn valid_userinfo(value: &str, allow_colon: bool) -> bool {
+    value.bytes().all(|b| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%@".contains(&b) || (allow_colon && b == b':'))
+}
+
+/// A proxy override as the client sees it, without the password
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProxyOverrideInfo {
+    protocol: ProxyProtocol,
+    host: String,
+    port: u16,
+    username: Option<String>,
+    has_password: bool,
+}
+
+/// [ProxySettings] as the client sees them
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProxySettingsInfo {
+    contexts: HashMap<String, ProxyOverrideInfo>,
+}
+
+/// Per-context proxy overrides, keyed by context name
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProxySettings {
+    #[serde(default)]
+    pub(crate) contexts: HashMap<String, ProxyOverride>,
+}
+
+impl ProxySettings {
+    pub(crate) fn load(app: &AppHandle) -> ProxySettings {
+        settings::load(app, PROXY_SETTINGS_FILE)
+    }
+
+    /// Passwords are in there, so only the user may read the file
+    pub(crate) fn save(&self, app: &AppHandle) -> Result<(), String> {
+        settings::save_private(app, PROXY_SETTINGS_FILE, self)
+    }
+
+    /// The settings without passwords, for the client
+    pub(crate) fn info(&self) -> ProxySettingsInfo {
+        let contexts = self.contexts.iter()
+            .map(|(context, proxy)| (context.clone(), ProxyOverrideInfo {
+                protocol: proxy.protocol,
+                host: proxy.host.clone(),
+                port: proxy.port,
+                username: proxy.username.clone(),
+                has_password: proxy.password.as_deref().is_some_and(|p| !p.is_empty()),
+            }))
+            .collect();
+        ProxySettingsInfo { contexts }
+    }
+
+    /// Fills in the passwords the client left unset from `saved`, where the user is the same,
+    /// and checks that every override can be used
+    pub(crate) fn merge_saved(mut self, saved: &ProxySettings) -> Result<ProxySettings, CommandError> {
+        for (context, proxy) in self.contexts.iter_mut() {
+            if proxy.password.is_none() {
+                proxy.password = saved.contexts.get(context)
+                    .filter(|s| s.username.is_some() && s.username == proxy.username)
+                    .and_then(|s| s.password.clone());
+            }
+            proxy.check()?;
+        }
+        Ok(self)
+    }
+
+    /// Applies the override for `context_name`, if there is one.
+    /// Otherwise the `proxy-url` from the kubeconfig cluster entry (already in `config`) is used.
+    pub(crate) fn apply(&self, context_name: &str, config: &mut Config) -> Result<(), CommandError> {
+        if let Some(proxy) = self.contexts.get(context_name) {
+            config.proxy_url = Some(proxy.to_uri()?);
+        }
+        Ok(())
+    }
+}
+
+/// The proxy URL with any credentials removed, for showing to the user
+pub(crate) fn display_proxy_url(uri: &Uri) -> String {
+    let authority = uri.authority().map(|a| a.as_str()).unwrap_or_default();
+    let host = authority.rsplit_once('@').map(|(_, host)| host).unwrap_or(authority);
+    format!("{}://{}", uri.scheme_str().unwrap_or_default(), host)
+}
+
+/// Builds the client for `config`.
+/// kube connects to SOCKS5 proxies without authentication, so when the proxy URL has credentials
+/// the client is put together here, with the same layers kube uses and a SOCKS5 connector that sends them.
+pub(crate) fn client(config: Config) -> Result<Client, kube::Error> {
+    let Some(proxy_url) = config.proxy_url.clone().filter(|uri| uri.scheme_str() == Some("socks5")) else {
+        return Client::try_from(config);
+    };
+    let Some((userinfo, host)) = proxy_url.authority().and_then(|a| a.as_str().rsplit_once('@')) else {
+        return Client::try_from(config);
+    };
+    let (username, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+    let proxy_dst = format!("socks5://{}", host).parse::<Uri>()
+        .map_err(|e| kube::Error::Service(e.into()))?;
+
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let socks = SocksV5::new(proxy_dst, http).with_auth(username.to_string(), password.to_string());
+    let mut connector = TimeoutConnector::new(config.rustls_https_connector_with_connector(socks)?);
+    connector.set_connect_timeout(config.connect_timeout);
+    connector.set_read_timeout(config.read_timeout);
+    connector.set_write_timeout(config.write_timeout);
+    let client: hyper_util::client::legacy::Client<_, Body> =
+        hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build(connector);
+
+    let service = ServiceBuilder::new()
+        .layer(config.base_uri_layer())
+        .option_layer(config.auth_layer()?)
+        .layer(config.extra_headers_layer()?)
+        .map_err(BoxError::from)
+        .service(client);
+    Ok(Client::new(service, config.default_namespace))
+}
+
+/// Whether the request failed to connect. With a proxy, that's the connection to the proxy
+/// or the tunnel through it, both made by the connector.
+pub(crate) fn is_connect_error(e: &kube::Error) -> bool {
+    let kube::Error::Service(e) = e else { return false };
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(e.as_ref());
+    while let Some(error) = source {
+        if error.downcast_ref::<hyper_util::client::legacy::Error>().is_some_and(|e| e.is_connect()) {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// Converts a request error, blaming the proxy when one is configured and the connection through it failed.
+/// Everything else (API errors, timeouts, broken responses) is reported as it is.
+pub(crate) fn request_error(proxy_url: Option<&String>, e: kube::Error) -> CommandError {
+    match proxy_url {
+        Some(proxy_url) if is_connect_error(&e) => CommandError::Proxy {
+            proxy_url: proxy_url.clone(),
+            message: e.to_string(),
+        },
+        _ => e.into(),
+    }
+}
@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// A page of a watched list as requested by the client
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListQuery {
+    /// JSONPath of the field to sort by, e.g. `$.metadata.creationTimestamp` or `.status.phase`
+    pub(crate) sort_by: Option<String>,
+    #[serde(default)]
+    pub(crate) descending: bool,
+    /// See [Filter::parse]
+    pub(crate) filter: Option<String>,
+    #[serde(default)]
+    pub(crate) offset: usize,
+    pub(crate) limit: Option<usize>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueryRow {
+    pub(crate) context: String,
+    pub(crate) resource: Value,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueryWindow {
+    /// Number of objects matching the filter
+    pub(crate) total: usize,
+    pub(crate) offset: usize,
+    pub(crate) rows: Vec<QueryRow>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// The subset of JSONPath needed to point at a field: `$.a.b`, `.a['b.c']`, `a.b[0]`
+#[derive(Clone, Debug, PartialEq)]
+struct JsonPath(Vec<Segment>);
+
+impl JsonPath {
+    fn parse(path: &str) -> Result<JsonPath, String> {
+        let path = path.trim();
+        let path = path.strip_prefix('$').unwrap_or(path);
+        let mut segments = Vec::new();
+        let mut chars = path.chars();
+        let mut field = String::new();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    if !field.is_empty() {
+                        segments.push(Segment::Field(std::mem::take(&mut field)));
+                    }
+                }
+                '[' => {
+                    if !field.is_empty() {
+                        segments.push(Segment::Field(std::mem::take(&mut field)));
+                    }
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(format!("unclosed [ in {}", path)),
+                        }
+                    }
+                    let inner = inner.trim();
+                    let quoted = inner.strip_prefix('\'').and_then(|i| i.strip_suffix('\''))
+                        .or_else(|| inner.strip_prefix('"').and_then(|i| i.strip_suffix('"')));
+                    match quoted {
+                        Some(name) => segments.push(Segment::Field(name.to_string())),
+                        None => segments.push(Segment::Index(
+                            inner.parse().map_err(|_| format!("invalid index [{}] in {}", inner, path))?,
+                        )),
+                    }
+                }
+                c => field.push(c),
+            }
+        }
+        if !field.is_empty() {
+            segments.push(Segment::Field(field));
+        }
+        if segments.is_empty() {
+            return Err("empty path".to_string());
+        }
+        Ok(JsonPath(segments))
+    }
+
+    fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.0.iter().try_fold(value, |value, segment| match segment {
+            Segment::Field(name) => value.get(name),
+            Segment::Index(index) => value.get(index),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Contains,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+/// At the same position the longest operator wins, so `>=` isn't read as `>`
+const OPERATORS: [(&str, Operator); 7] = [
+    ("==", Operator::Eq),
+    ("!=", Operator::Ne),
+    ("~=", Operator::Contains),
+    (">=", Operator::Ge),
+    ("<=", Operator::Le),
+    (">", Operator::Gt),
+    ("<", Operator::Lt),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Clause {
+    /// Bare text, matched case-insensitively against the name and namespace
+    Text(String),
+    Compare { path: JsonPath, operator: Operator, value: String },
+}
+
+/// Where `pattern` first occurs in `text` outside of '...' or "..." quotes
+fn find_unquoted(text: &str, pattern: &str) -> Option<usize> {
+    let mut quote = None;
+    for (at, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if text[at..].starts_with(pattern) => return Some(at),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Splits a filter at the `&&`s that aren't quoted
+fn split_clauses(mut expression: &str) -> Vec<&str> {
+    let mut clauses = Vec::new();
+    while let Some(at) = find_unquoted(expression, "&&") {
+        clauses.push(&expression[..at]);
+        expression = &expression[at + 2..];
+    }
+    clauses.push(expression);
+    clauses
+}
+
+/// Clauses joined with `&&`, each either `<path> <op> <value>` or bare text.
+/// Operators are `==`, `!=`, `~=` (contains, ignoring case), `<`, `<=`, `>`, `>=`.
+/// Quoted keys and values may contain operators and `&&`.
+/// e.g. `.status.phase != Running && nginx`
+#[derive(Clone, Debug, PartialEq)]
+struct Filter(Vec<Clause>);
+
+impl Filter {
+    fn parse(expression: &str) -> Result<Filter, String> {
+        let mut clauses = Vec::new();
+        for clause in split_clauses(expression).into_iter().map(str::trim).filter(|c| !c.is_empty()) {
+            let operator = OPERATORS.iter()
+                .filter_map(|(symbol, operator)| find_unquoted(clause, symbol).map(|at| (at, *symbol, *operator)))
+                .min_by_key(|(at, symbol, _)| (*at, std::cmp::Reverse(symbol.len())));
+            match operator {
+                Some((at, symbol, operator)) => {
+                    let value = clause[at + symbol.len()..].trim();
+                    let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                        .unwrap_or(value);
+                    clauses.push(Clause::Compare {
+                        path: JsonPath::parse(&clause[..at])?,
+                        operator,
+                        value: value.to_string(),
+                    });
+                }
+                None => clauses.push(Clause::Text(clause.to_lowercase())),
+            }
+        }
+        Ok(Filter(clauses))
+    }
+
+    fn matches(&self, resource: &Value) -> bool {
+        self.0.iter().all(|clause| match clause {
+            Clause::Text(text) => ["name", "namespace"].iter().any(|field| {
+                resource["metadata"][field].as_str().is_some_and(|v| v.to_lowercase().contains(text))
+            }),
+            Clause::Compare { path, operator, value } => {
+                let field = path.get(resource);
+                compare_clause(field, *operator, value)
+            }
+        })
+    }
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn compare_clause(field: Option<&Value>, operator: Operator, value: &str) -> bool {
+    let Some(field) = field.filter(|f| !f.is_null()) else {
+        // A missing field is only "not equal" to anything
+        return operator == Operator::Ne;
+    };
+    let ordering = match (field.as_f64(), value.parse::<f64>()) {
+        (Some(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(as_text(field).as_str().cmp(value)),
+    };
+    match operator {
+        Operator::Eq => ordering == Some(Ordering::Equal),
+        Operator::Ne => ordering != Some(Ordering::Equal),
+        Operator::Contains => as_text(field).to_lowercase().contains(&value.to_lowercase()),
+        Operator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        Operator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Operator::Gt => ordering == Some(Ordering::Greater),
+        Operator::Lt => ordering == Some(Ordering::Less),
+    }
+}
+
+/// Orders values of a sort field: numbers numerically and before everything else, which is ordered by its text
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => as_text(a).cmp(&as_text(b)),
+    }
+}
+
+/// Namespace, name and context, the order of rows the sort field doesn't decide
+fn identity<'a>((context, resource): &(&'a str, &'a Value)) -> (&'a str, &'a str, &'a str) {
+    (
+        resource["metadata"]["namespace"].as_str().unwrap_or_default(),
+        resource["metadata"]["name"].as_str().unwrap_or_default(),
+        context,
+    )
+}
+
+/// A [ListQuery] with its paths and filter parsed
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledQuery {
+    sort_by: Option<JsonPath>,
+    descending: bool,
+    filter: Option<Filter>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl ListQuery {
+    pub(crate) fn compile(&self) -> Result<CompiledQuery, String> {
+        Ok(CompiledQuery {
+            sort_by: self.sort_by.as_deref().filter(|s| !s.trim().is_empty()).map(JsonPath::parse).transpose()?,
+            descending: self.descending,
+            filter: self.filter.as_deref().map(Filter::parse).transpose()?,
+            offset: self.offset,
+            limit: self.limit,
+        })
+    }
+}
+
+impl CompiledQuery {
+    /// Whether the filter lets `resource` into the window
+    pub(crate) fn matches(&self, resource: &Value) -> bool {
+        self.filter.as_ref().is_none_or(|f| f.matches(resource))
+    }
+
+    /// Filters and sorts `items` (context, resource) and returns the requested window.
+    /// Objects without the sort field go last; ties are broken by namespace, name and context.
+    pub(crate) fn window<'a>(&self, items: impl Iterator<Item = (&'a str, &'a Value)>) -> QueryWindow {
+        let mut matching: Vec<(&str, &Value)> = items
+            .filter(|(_, resource)| self.matches(resource))
+            .collect();
+
+        matching.sort_by(|a, b| {
+            let by_field = match &self.sort_by {
+                Some(path) => match (path.get(a.1).filter(|v| !v.is_null()), path.get(b.1).filter(|v| !v.is_null())) {
+                    (Some(x), Some(y)) => {
+                        let ordering = compare_values(x, y);
+                        if self.descending { ordering.reverse() } else { ordering }
+                    }
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                None => Ordering::Equal,
+            };
+            by_field.then_with(|| identity(a).cmp(&identity(b)))
+        });
+
+        let total = matching.len();
+        let rows = matching.into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|(context, resource)| QueryRow { context: context.to_string(), resource: resource.clone() })
+            .collect();
+        QueryWindow { total, offset: self.offset, rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pod(name: &str, status: Value) -> Value {
+        json!({ "metadata": { "name": name, "namespace": "default" }, "status": status })
+    }
+
+    fn names(window: &QueryWindow) -> Vec<&str> {
+        window.rows.iter().map(|row| row.resource["metadata"]["name"].as_str().unwrap()).collect()
+    }
+
+    fn query(sort_by: Option<&str>, filter: Option<&str>, offset: usize, limit: Option<usize>) -> CompiledQuery {
+        ListQuery {
+            sort_by: sort_by.map(str::to_string),
+            descending: false,
+            filter: filter.map(str::to_string),
+            offset,
+            limit,
+        }.compile().unwrap()
+    }
+
+    #[test]
+    fn bracket_and_quoted_keys() {
+        let path = JsonPath::parse("$.metadata.labels['app.kubernetes.io/name']").unwrap();
+        assert_eq!(path, JsonPath(vec![
+            Segment::Field("metadata".to_string()),
+            Segment::Field("labels".to_string()),
+            Segment::Field("app.kubernetes.io/name".to_string()),
+        ]));
+        assert_eq!(JsonPath::parse(r#".spec.containers[0]["image"]"#).unwrap(), JsonPath(vec![
+            Segment::Field("spec".to_string()),
+            Segment::Field("containers".to_string()),
+            Segment::Index(0),
+            Segment::Field("image".to_string()),
+        ]));
+
+        let labelled = json!({ "metadata": { "labels": { "app.kubernetes.io/name": "nginx" } } });
+        assert!(Filter::parse(".metadata.labels['app.kubernetes.io/name'] == nginx").unwrap().matches(&labelled));
+        assert!(!Filter::parse(".metadata.labels['app.kubernetes.io/name'] == redis").unwrap().matches(&labelled));
+    }
+
+    #[test]
+    fn operators_and_ands_inside_quotes() {
+        let filter = Filter::parse(".metadata.labels['a==b'] == 'x && y' && nginx").unwrap();
+        assert_eq!(filter.0.len(), 2);
+        assert_eq!(filter.0[0], Clause::Compare {
+            path: JsonPath(vec![Segment::Field("metadata".to_string()), Segment::Field("labels".to_string()), Segment::Field("a==b".to_string())]),
+            operator: Operator::Eq,
+            value: "x && y".to_string(),
+        });
+        assert_eq!(filter.0[1], Clause::Text("nginx".to_string()));
+    }
+
+    #[test]
+    fn longest_operator_wins() {
+        let filter = Filter::parse(".status.replicas >= 2").unwrap();
+        assert!(matches!(&filter.0[..], [Clause::Compare { operator: Operator::Ge, value, .. }] if value == "2"));
+        assert!(filter.matches(&json!({ "status": { "replicas": 2 } })));
+        assert!(filter.matches(&json!({ "status": { "replicas": 3 } })));
+        assert!(!filter.matches(&json!({ "status": { "replicas": 1 } })));
+
+        let filter = Filter::parse(".status.replicas > 2").unwrap();
+        assert!(!filter.matches(&json!({ "status": { "replicas": 2 } })));
+        assert!(filter.matches(&json!({ "status": { "replicas": 3 } })));
+    }
+
+    #[test]
+    fn missing_fields_only_match_not_equal() {
+        let missing = json!({ "metadata": { "name": "a" } });
+        let null = pod("b", Value::Null);
+        for resource in [&missing, &null] {
+            assert!(Filter::parse(".status.phase != Running").unwrap().matches(resource));
+            assert!(!Filter::parse(".status.phase == Running").unwrap().matches(resource));
+            assert!(!Filter::parse(".status.phase ~= Run").unwrap().matches(resource));
+            assert!(!Filter::parse(".status.phase < Running").unwrap().matches(resource));
+        }
+    }
+
+    #[test]
+    fn numbers_compare_numerically_and_sort_before_text() {
+        // A number in the object against a number in the filter compares numerically, a string textually
+        assert!(compare_clause(Some(&json!(10)), Operator::Gt, "9"));
+        assert!(compare_clause(Some(&json!("10")), Operator::Lt, "9"));
+
+        let items = [
+            pod("a", json!({ "value": "10x" })),
+            pod("b", json!({ "value": 9 })),
+            pod("c", json!({ "value": 10 })),
+            pod("d", json!({ "value": "abc" })),
+            pod("e", json!({})),
+        ];
+        let window = query(Some(".status.value"), None, 0, None).window(items.iter().map(|r| ("ctx", r)));
+        assert_eq!(names(&window), ["b", "c", "a", "d", "e"]);
+    }
+
+    #[test]
+    fn windows_past_the_end() {
+        let items: Vec<Value> = (0..5).map(|i| pod(&format!("pod-{}", i), json!({}))).collect();
+
+        let window = query(None, None, 3, Some(10)).window(items.iter().map(|r| ("ctx", r)));
+        assert_eq!(window.total, 5);
+        assert_eq!(window.offset, 3);
+        assert_eq!(names(&window), ["pod-3", "pod-4"]);
+
+        let window = query(None, None, 7, Some(2)).window(items.iter().map(|r| ("ctx", r)));
+        assert_eq!(window.total, 5);
+        assert_eq!(window.offset, 7);
+        assert!(window.rows.is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+fn path(app: &AppHandle, file_name: &str) -> Option<PathBuf> {
+    app.path().app_config_dir().ok().map(|dir| dir.join(file_name))
+}
+
+/// Reads a JSON settings file from the app config directory.
+/// A missing or unreadable file is treated as the default settings.
+pub(crate) fn load<T: DeserializeOwned + Default>(app: &AppHandle, file_name: &str) -> T {
+    let Some(path) = path(app, file_name) else {
+        return T::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Failed to parse {}: {}", path.display(), e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Writes a JSON settings file to the app config directory
+pub(crate) fn save<T: Serialize>(app: &AppHandle, file_name: &str, value: &T) -> Result<(), String> {
+    write(app, file_name, value, false)
+}
+
+/// [save] for settings with secrets in them: on Unix, only the user can read the file
+pub(crate) fn save_private<T: Serialize>(app: &AppHandle, file_name: &str, value: &T) -> Result<(), String> {
+    write(app, file_name, value, true)
+}
+
+fn write<T: Serialize>(app: &AppHandle, file_name: &str, value: &T, private: bool) -> Result<(), String> {
+    let path = path(app, file_name).ok_or("no app config directory".to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(&path, data).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+    Ok(())
+}
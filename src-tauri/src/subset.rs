@@ -0,0 +1,417 @@
+use crate::object_cache::{ObjectCache, SharedObject};
+use crate::{ResourceListenEvent, SubscriptionKey};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// One comma-separated part of a label selector
+#[derive(Clone, Debug, PartialEq)]
+enum LabelRequirement {
+    Exists(String),
+    NotExists(String),
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, BTreeSet<String>),
+    NotIn(String, BTreeSet<String>),
+}
+
+impl LabelRequirement {
+    /// Parses `key`, `!key`, `key=value`, `key==value`, `key!=value`, `key in (a,b)` and `key notin (a,b)`
+    fn parse(requirement: &str) -> Option<LabelRequirement> {
+        if let Some(key) = requirement.strip_prefix('!') {
+            return Some(LabelRequirement::NotExists(label_key(key)?));
+        }
+        if let Some(open) = requirement.find('(') {
+            let values = requirement[open + 1..].strip_suffix(')')?
+                .split(',')
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .collect();
+            let (key, operator) = requirement[..open].trim().rsplit_once(char::is_whitespace)?;
+            return match operator {
+                "in" => Some(LabelRequirement::In(label_key(key)?, values)),
+                "notin" => Some(LabelRequirement::NotIn(label_key(key)?, values)),
+                _ => None,
+            };
+        }
+        if let Some((key, value)) = requirement.split_once("!=") {
+            return Some(LabelRequirement::NotEquals(label_key(key)?, value.trim().to_string()));
+        }
+        if let Some((key, value)) = requirement.split_once("==").or_else(|| requirement.split_once('=')) {
+            return Some(LabelRequirement::Equals(label_key(key)?, value.trim().to_string()));
+        }
+        Some(LabelRequirement::Exists(label_key(requirement)?))
+    }
+
+    /// Same rules as the API server: `!=` and `notin` also match objects without the label
+    fn matches(&self, labels: Option<&serde_json::Map<String, Value>>) -> bool {
+        let get = |key: &str| labels.and_then(|l| l.get(key)).and_then(Value::as_str);
+        match self {
+            LabelRequirement::Exists(key) => get(key).is_some(),
+            LabelRequirement::NotExists(key) => get(key).is_none(),
+            LabelRequirement::Equals(key, value) => get(key) == Some(value.as_str()),
+            LabelRequirement::NotEquals(key, value) => get(key) != Some(value.as_str()),
+            LabelRequirement::In(key, values) => get(key).is_some_and(|v| values.contains(v)),
+            LabelRequirement::NotIn(key, values) => get(key).is_none_or(|v| !values.contains(v)),
+        }
+    }
+}
+
+/// A label key, or `None` for anything we don't understand (e.g. the `<`/`>` operators)
+fn label_key(key: &str) -> Option<String> {
+    let key = key.trim();
+    let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c));
+    valid.then(|| key.to_string())
+}
+
+/// One comma-separated part of a field selector
+#[derive(Clone, Debug, PartialEq)]
+struct FieldRequirement {
+    field: String,
+    value: String,
+    equals: bool,
+}
+
+impl FieldRequirement {
+    fn parse(requirement: &str) -> Option<FieldRequirement> {
+        let (field, value, equals) = match requirement.split_once("!=") {
+            Some((field, value)) => (field, value, false),
+            None => {
+                let (field, value) = requirement.split_once("==").or_else(|| requirement.split_once('='))?;
+                (field, value, true)
+            }
+        };
+        Some(FieldRequirement { field: field.trim().to_string(), value: value.trim().to_string(), equals })
+    }
+
+    /// Whether we can check it ourselves: the fields every resource type supports.
+    /// Others (e.g. `status.phase` on pods) are only known to the API server.
+    fn evaluable(&self) -> bool {
+        matches!(self.field.as_str(), "metadata.name" | "metadata.namespace")
+    }
+
+    fn matches(&self, resource: &Value) -> bool {
+        let value = match self.field.as_str() {
+            "metadata.name" => resource["metadata"]["name"].as_str(),
+            "metadata.namespace" => resource["metadata"]["namespace"].as_str(),
+            _ => return false,
+        };
+        (value.unwrap_or_default() == self.value) == self.equals
+    }
+}
+
+/// Splits a selector on the commas that aren't inside `in (...)`
+fn split_requirements(selector: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&selector[start..]);
+    parts.into_iter().map(str::trim).filter(|p| !p.is_empty()).collect()
+}
+
+fn parse_selector<T>(selector: Option<&str>, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    selector.map(split_requirements).unwrap_or_default().into_iter().map(parse).collect()
+}
+
+/// What `narrow` asks for on top of `broad`, or `None` if `broad` asks for something `narrow` doesn't
+fn extra_requirements<T: PartialEq>(broad: Vec<T>, narrow: Vec<T>) -> Option<Vec<T>> {
+    if !broad.iter().all(|r| narrow.contains(r)) {
+        return None;
+    }
+    Some(narrow.into_iter().filter(|r| !broad.contains(r)).collect())
+}
+
+/// The namespaces a key watches, `None` for all of them (same rules as `spawn_shared_watcher`)
+fn namespace_scope(key: &SubscriptionKey) -> Option<Vec<String>> {
+    match (&key.namespaces, &key.namespace) {
+        (Some(namespaces), _) if !namespaces.is_empty() => Some(namespaces.clone()),
+        (Some(_), _) => None,
+        (None, Some(namespace)) => Some(vec![namespace.clone()]),
+        (None, None) => None,
+    }
+}
+
+/// The part of a broader watcher's list that a narrower subscription asked for
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SubsetFilter {
+    namespaces: Option<HashSet<String>>,
+    /// Set when the subscription is for a single object
+    name: Option<String>,
+    labels: Vec<LabelRequirement>,
+    fields: Vec<FieldRequirement>,
+}
+
+impl SubsetFilter {
+    pub(crate) fn matches(&self, resource: &Value) -> bool {
+        let metadata = &resource["metadata"];
+        self.namespaces.as_ref().is_none_or(|ns| metadata["namespace"].as_str().is_some_and(|n| ns.contains(n)))
+            && self.name.as_ref().is_none_or(|name| metadata["name"].as_str() == Some(name.as_str()))
+            && self.labels.iter().all(|r| r.matches(metadata["labels"].as_object()))
+            && self.fields.iter().all(|r| r.matches(resource))
+    }
+}
+
+/// How a watcher for `broad` can serve a subscription for `narrow`, if it has everything `narrow` would list.
+/// Only selectors we can evaluate on our side qualify: any label selector, and field selectors on name and namespace.
+pub(crate) fn covering_filter(broad: &SubscriptionKey, narrow: &SubscriptionKey) -> Option<SubsetFilter> {
+    let same_resource = broad.group == narrow.group
+        && broad.api_version == narrow.api_version
+        && broad.resource_plural == narrow.resource_plural
+        && broad.metadata_only == narrow.metadata_only
+        && broad.as_table == narrow.as_table;
+    if !same_resource || broad.name.is_some() {
+        return None;
+    }
+
+    let namespaces = match (namespace_scope(broad), namespace_scope(narrow)) {
+        (None, None) => None,
+        (None, Some(narrow)) => Some(narrow),
+        (Some(broad), Some(narrow)) if narrow.iter().all(|ns| broad.contains(ns)) => Some(narrow),
+        _ => return None,
+    };
+    let namespaces = namespaces.map(|ns| ns.into_iter().collect());
+
+    if narrow.name.is_some() {
+        // Single objects are watched without selectors, so the list mustn't leave any object out
+        if broad.label_selector.is_some() || broad.field_selector.is_some() {
+            return None;
+        }
+        return Some(SubsetFilter { namespaces, name: narrow.name.clone(), ..Default::default() });
+    }
+
+    let labels = if broad.label_selector == narrow.label_selector {
+        Vec::new()
+    } else {
+        extra_requirements(
+            parse_selector(broad.label_selector.as_deref(), LabelRequirement::parse)?,
+            parse_selector(narrow.label_selector.as_deref(), LabelRequirement::parse)?,
+        )?
+    };
+    let fields = if broad.field_selector == narrow.field_selector {
+        Vec::new()
+    } else {
+        extra_requirements(
+            parse_selector(broad.field_selector.as_deref(), FieldRequirement::parse)?,
+            parse_selector(narrow.field_selector.as_deref(), FieldRequirement::parse)?,
+        )?
+    };
+    if !fields.iter().all(FieldRequirement::evaluable) {
+        return None;
+    }
+
+    Some(SubsetFilter { namespaces, name: None, labels, fields })
+}
+
+fn uid(resource: &Value) -> Option<String> {
+    resource["metadata"]["uid"].as_str().map(str::to_string)
+}
+
+/// Narrows a broader watcher's events for one bridge.
+/// Objects that stop matching (e.g. a label was removed) are sent as deleted.
+pub(crate) struct SubsetStream {
+    filter: Arc<SubsetFilter>,
+    /// Objects the client has and hasn't been told were deleted
+    visible: HashSet<String>,
+}
+
+impl SubsetStream {
+    pub(crate) fn new(filter: Arc<SubsetFilter>) -> Self {
+        SubsetStream { filter, visible: HashSet::new() }
+    }
+
+    /// The objects of a cache snapshot in the subset, for a replay that replaces the client's list
+    pub(crate) fn snapshot(&mut self, objects: Vec<SharedObject>) -> Vec<SharedObject> {
+        self.visible.clear();
+        objects.into_iter()
+            .filter(|resource| self.filter.matches(resource))
+            .inspect(|resource| {
+                if let Some(uid) = uid(resource) {
+                    self.visible.insert(uid);
+                }
+            })
+            .collect()
+    }
+
+    /// Sent after a replay or an initial list, single object subscriptions expect to hear when there's nothing
+    pub(crate) fn not_found(&self) -> Option<ResourceListenEvent> {
+        (self.filter.name.is_some() && self.visible.is_empty()).then_some(ResourceListenEvent::SingleResourceNotFoundOrDeleted)
+    }
+
+    /// What the subscription sees of an event from the broader watcher, whose cache is `cache`
+    pub(crate) fn apply(&mut self, event: ResourceListenEvent, cache: &RwLock<ObjectCache>) -> Vec<ResourceListenEvent> {
+        match event {
+            ResourceListenEvent::Init => {
+                self.visible.clear();
+                vec![ResourceListenEvent::Init]
+            }
+            ResourceListenEvent::InitApply { resource } => {
+                if !self.filter.matches(&resource) {
+                    return Vec::new();
+                }
+                if let Some(uid) = uid(&resource) {
+                    self.visible.insert(uid);
+                }
+                vec![ResourceListenEvent::InitApply { resource }]
+            }
+            ResourceListenEvent::InitDone => {
+                std::iter::once(ResourceListenEvent::InitDone).chain(self.not_found()).collect()
+            }
+            ResourceListenEvent::Apply { resource } => self.update(resource),
+            // The patch is against the version the broader watcher had, which the client may not have
+            // if the object only just started matching, so send it whole
+            ResourceListenEvent::Patch { uid, .. } => {
+                match cache.read().ok().and_then(|c| c.get(&uid).cloned()) {
+                    Some(resource) => self.update(resource),
+                    None => Vec::new(),
+                }
+            }
+            ResourceListenEvent::Delete { resource } => {
+                if uid(&resource).is_some_and(|uid| self.visible.remove(&uid)) {
+                    self.deleted(resource)
+                } else {
+                    Vec::new()
+                }
+            }
+            other => vec![other],
+        }
+    }
+
+    fn update(&mut self, resource: SharedObject) -> Vec<ResourceListenEvent> {
+        let Some(uid) = uid(&resource) else { return Vec::new() };
+        if self.filter.matches(&resource) {
+            self.visible.insert(uid);
+            vec![ResourceListenEvent::Apply { resource }]
+        } else if self.visible.remove(&uid) {
+            self.deleted(resource)
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn deleted(&self, resource: SharedObject) -> Vec<ResourceListenEvent> {
+        let mut events = vec![ResourceListenEvent::Delete { resource }];
+        events.extend(self.not_found());
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(namespace: Option<&str>, namespaces: Option<&[&str]>, label_selector: Option<&str>) -> SubscriptionKey {
+        SubscriptionKey {
+            group: "apps".to_string(),
+            api_version: "v1".to_string(),
+            resource_plural: "deployments".to_string(),
+            namespace: namespace.map(str::to_string),
+            name: None,
+            namespaces: namespaces.map(|ns| ns.iter().map(|n| n.to_string()).collect()),
+            label_selector: label_selector.map(str::to_string),
+            field_selector: None,
+            metadata_only: false,
+            as_table: false,
+        }
+    }
+
+    fn object(namespace: &str, labels: Value) -> Value {
+        json!({ "metadata": { "name": "web", "namespace": namespace, "labels": labels } })
+    }
+
+    fn values(values: &[&str]) -> BTreeSet<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_label_requirements() {
+        assert_eq!(LabelRequirement::parse("app"), Some(LabelRequirement::Exists("app".to_string())));
+        assert_eq!(LabelRequirement::parse("!app"), Some(LabelRequirement::NotExists("app".to_string())));
+        assert_eq!(LabelRequirement::parse("app = web"), Some(LabelRequirement::Equals("app".to_string(), "web".to_string())));
+        assert_eq!(LabelRequirement::parse("app==web"), LabelRequirement::parse("app=web"));
+        assert_eq!(LabelRequirement::parse("tier!=cache"), Some(LabelRequirement::NotEquals("tier".to_string(), "cache".to_string())));
+        assert_eq!(
+            LabelRequirement::parse("app.kubernetes.io/name in (web, api)"),
+            Some(LabelRequirement::In("app.kubernetes.io/name".to_string(), values(&["api", "web"]))),
+        );
+        assert_eq!(LabelRequirement::parse("env notin (dev)"), Some(LabelRequirement::NotIn("env".to_string(), values(&["dev"]))));
+        assert_eq!(LabelRequirement::parse("env within (dev)"), None);
+        assert_eq!(LabelRequirement::parse("replicas > 2"), None);
+    }
+
+    #[test]
+    fn negative_requirements_match_objects_without_the_label() {
+        let unlabelled = object("default", json!({}));
+        let no_labels = json!({ "metadata": { "name": "web" } });
+        for requirement in ["tier!=cache", "env notin (dev,test)", "!tier"] {
+            let requirement = LabelRequirement::parse(requirement).unwrap();
+            assert!(requirement.matches(unlabelled["metadata"]["labels"].as_object()));
+            assert!(requirement.matches(no_labels["metadata"]["labels"].as_object()));
+        }
+        for requirement in ["tier=cache", "env in (dev,test)", "tier"] {
+            let requirement = LabelRequirement::parse(requirement).unwrap();
+            assert!(!requirement.matches(unlabelled["metadata"]["labels"].as_object()));
+        }
+
+        let cache = object("default", json!({ "tier": "cache", "env": "dev" }));
+        assert!(!LabelRequirement::parse("tier!=cache").unwrap().matches(cache["metadata"]["labels"].as_object()));
+        assert!(!LabelRequirement::parse("env notin (dev,test)").unwrap().matches(cache["metadata"]["labels"].as_object()));
+    }
+
+    #[test]
+    fn splits_requirements_outside_parentheses() {
+        assert_eq!(split_requirements("app=web, env in (dev,test),!tier"), ["app=web", "env in (dev,test)", "!tier"]);
+        assert_eq!(split_requirements(" , app ,"), ["app"]);
+        assert!(split_requirements("").is_empty());
+    }
+
+    #[test]
+    fn covers_narrower_selectors_in_any_order() {
+        let broad = key(None, None, Some("tier=web,app=shop"));
+        let narrow = key(None, None, Some("app==shop,env in (dev),tier=web"));
+        let filter = covering_filter(&broad, &narrow).unwrap();
+        assert_eq!(filter.labels, vec![LabelRequirement::In("env".to_string(), values(&["dev"]))]);
+        assert!(filter.matches(&object("a", json!({ "app": "shop", "tier": "web", "env": "dev" }))));
+        assert!(!filter.matches(&object("a", json!({ "app": "shop", "tier": "web", "env": "prod" }))));
+
+        // The same requirements in another order need no filtering
+        let reordered = covering_filter(&broad, &key(None, None, Some("app=shop,tier=web"))).unwrap();
+        assert!(reordered.labels.is_empty());
+
+        // A broad watcher that asks for something the narrow one doesn't can't serve it
+        assert!(covering_filter(&narrow, &broad).is_none());
+        assert!(covering_filter(&key(None, None, Some("app!=shop")), &key(None, None, None)).is_none());
+    }
+
+    #[test]
+    fn scopes_namespaces() {
+        let all = key(None, None, None);
+        let one = key(Some("a"), None, None);
+        let two = key(None, Some(&["a", "b"]), None);
+
+        let filter = covering_filter(&all, &one).unwrap();
+        assert!(filter.matches(&object("a", json!({}))));
+        assert!(!filter.matches(&object("b", json!({}))));
+
+        let filter = covering_filter(&two, &one).unwrap();
+        assert!(filter.matches(&object("a", json!({}))));
+        assert!(!filter.matches(&object("c", json!({}))));
+
+        assert!(covering_filter(&one, &two).is_none());
+        assert!(covering_filter(&one, &all).is_none());
+        assert!(covering_filter(&two, &key(Some("c"), None, None)).is_none());
+        // An empty namespace list watches every namespace
+        assert!(covering_filter(&key(None, Some(&[]), None), &two).is_some());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::health::{HealthReporter, WatchState};
+use crate::object_cache::ObjectCache;
+use crate::{cache_apply_value, cache_delete_value, cache_replace, params_for, watch_until_failed, ResourceListenEvent, WatchEnd};
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use kube::api::{DynamicObject, WatchEvent};
+use kube::runtime::watcher;
+use kube::Api;
+use serde::Deserialize;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::http::header::{HeaderValue, ACCEPT};
+
+/// Asks for a Table, falling back to the old beta version.
+/// Servers that can make neither answer 406, rather than a plain list that would look like an empty table.
+const TABLE_ACCEPT: &str = "application/json;as=Table;v=v1;g=meta.k8s.io,application/json;as=Table;v=v1beta1;g=meta.k8s.io";
+
+/// How long to wait before listing again after a failed list
+const RELIST_DELAY: Duration = Duration::from_secs(5);
+
+/// `meta.k8s.io/v1 Table`, the server-side printing `kubectl get` uses
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Table {
+    /// Checked, in case a server ignores the Accept header and sends a plain list
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    column_definitions: Vec<serde_json::Value>,
+    #[serde(default)]
+    rows: Vec<TableRow>,
+    #[serde(default)]
+    metadata: TableMeta,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct TableMeta {
+    resource_version: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct TableRow {
+    cells: Vec<serde_json::Value>,
+    /// PartialObjectMetadata unless the request asks for the full object
+    object: Option<serde_json::Value>,
+}
+
+impl Table {
+    /// Sends the rows to the cache, deleted or not, and returns the newest resourceVersion among them
+    fn apply_rows(self, mut apply: impl FnMut(String, serde_json::Value)) -> Option<String> {
+        let mut version = None;
+        for (uid, resource) in self.rows.into_iter().filter_map(TableRow::into_resource) {
+            if let Some(v) = resource["metadata"]["resourceVersion"].as_str() {
+                version = Some(v.to_string());
+            }
+            apply(uid, resource);
+        }
+        version
+    }
+}
+
+impl TableRow {
+    /// The row as the client sees it: `{ metadata, cells }`, keyed by the object's uid like any other resource
+    fn into_resource(self) -> Option<(String, serde_json::Value)> {
+        let metadata = self.object?.get("metadata")?.clone();
+        let uid = metadata["uid"].as_str()?.to_string();
+        Some((uid, serde_json::json!({ "metadata": metadata, "cells": self.cells })))
+    }
+}
+
+fn table_request(request: Result<tauri::http::Request<Vec<u8>>, kube::core::request::Error>) -> Result<tauri::http::Request<Vec<u8>>, kube::Error> {
+    let mut request = request.map_err(kube::Error::BuildRequest)?;
+    request.headers_mut().insert(ACCEPT, HeaderValue::from_static(TABLE_ACCEPT));
+    Ok(request)
+}
+
+async fn list_table(api: &Api<DynamicObject>, wc: &watcher::Config) -> Result<Table, kube::Error> {
+    let (lp, _) = params_for(wc);
+    let request = table_request(kube::core::Request::new(api.resource_url()).list(&lp))?;
+    let table = api.clone().into_client().request::<Table>(request).await?;
+    if table.kind != "Table" {
+        let message = format!("the server sent a {} instead of a Table, subscribe without asTable", table.kind);
+        return Err(kube::Error::Service(message.into()));
+    }
+    Ok(table)
+}
+
+/// Watches one API from `resource_version` until the watch fails (usually 410 Gone), sending rows as they change
+async fn watch_table(
+    api: &Api<DynamicObject>,
+    wc: &watcher::Config,
+    resource_version: String,
+    tx: &tokio::sync::broadcast::Sender<ResourceListenEvent>,
+    cache: &RwLock<ObjectCache>,
+) -> WatchEnd {
+    // Column definitions only come with the list, and bookmarks wouldn't be Tables
+    let (_, wp) = params_for(wc);
+    let wp = &wp.disable_bookmarks();
+    let client = &api.clone().into_client();
+    let start = |version: String| async move {
+        match table_request(kube::core::Request::new(api.resource_url()).watch(wp, &version)) {
+            Ok(request) => client.request_events::<Table>(request).await.map(StreamExt::boxed),
+            Err(e) => Err(e),
+        }
+    };
+
+    watch_until_failed(resource_version, start, |event| match event {
+        WatchEvent::Added(table) | WatchEvent::Modified(table) => {
+            table.apply_rows(|uid, resource| cache_apply_value(cache, tx, uid, resource))
+        }
+        WatchEvent::Deleted(table) => {
+            table.apply_rows(|uid, resource| cache_delete_value(cache, tx, Some(uid.as_str()), resource))
+        }
+        WatchEvent::Bookmark(bookmark) => Some(bookmark.metadata.resource_version),
+        WatchEvent::Error(_) => None,
+    }).await
+}
+
+/// Source task body for Table subscriptions: lists every API as a Table, sends the rows,
+/// then watches all of them until one fails and lists again.
+/// Column definitions are kept in `columns` for bridges that join later.
+pub(crate) async fn run_table_watch(
+    apis: Vec<Api<DynamicObject>>,
+    wc: watcher::Config,
+    tx: tokio::sync::broadcast::Sender<ResourceListenEvent>,
+    cache: &RwLock<ObjectCache>,
+    columns: &RwLock<Option<serde_json::Value>>,
+    health: &HealthReporter,
+) {
+    loop {
+        health.set(WatchState::Relisting);
+
+        // 1. List, as one snapshot across all APIs
+        let mut rows = Vec::new();
+        let mut versions = Vec::new();
+        let mut column_definitions = None;
+        for api in &apis {
+            match list_table(api, &wc).await {
+                Ok(table) => {
+                    if column_definitions.is_none() && !table.column_definitions.is_empty() {
+                        column_definitions = Some(serde_json::Value::Array(table.column_definitions));
+                    }
+                    versions.push(table.metadata.resource_version.unwrap_or_default());
+                    rows.extend(table.rows.into_iter().filter_map(TableRow::into_resource));
+                }
+                Err(e) => {
+                    health.request_failed(&e, RELIST_DELAY);
+                    tokio::time::sleep(RELIST_DELAY).await;
+                    break;
+                }
+            }
+        }
+        if versions.len() != apis.len() {
+            continue;
+        }
+
+        if let Some(column_definitions) = column_definitions {
+            if let Ok(mut c) = columns.write() {
+                *c = Some(column_definitions.clone());
+            }
+            let _ = tx.send(ResourceListenEvent::TableColumns { columns: column_definitions });
+        }
+        cache_replace(cache, &tx, rows);
+        health.set(WatchState::Connected);
+
+        // 2. Watch every API until one of them fails
+        let watches: Vec<BoxFuture<WatchEnd>> = apis.iter().zip(versions)
+            .map(|(api, version)| watch_table(api, &wc, version, &tx, cache).boxed())
+            .collect();
+        match futures_util::future::select_all(watches).await.0 {
+            WatchEnd::Expired => health.set(WatchState::Desynced),
+            WatchEnd::Failed(message) => {
+                eprintln!("Table watch failed, relisting: {}", message);
+                health.set(WatchState::BackingOff { retry_in_ms: RELIST_DELAY.as_millis() as u64, message });
+                tokio::time::sleep(RELIST_DELAY).await;
+            }
+            WatchEnd::RequestFailed(e) => {
+                eprintln!("Table watch failed, relisting: {}", e);
+                health.request_failed(&e, RELIST_DELAY);
+                tokio::time::sleep(RELIST_DELAY).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::object_cache::SharedObject;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const WATCH_CACHE_DIR: &str = "watch-cache";
+/// Every persisted list by id, see [IndexEntry]
+const INDEX_FILE: &str = "index.json";
+/// Lists that haven't been saved for this long are deleted
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Above this, the lists saved longest ago are deleted
+const MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// A watched list as it was when its watcher stopped
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PersistedList {
+    /// Context and subscription key the list belongs to, checked on load in case file names collide
+    pub(crate) id: String,
+    /// To delete the list once the context is gone
+    #[serde(default)]
+    pub(crate) context: String,
+    /// The newest resourceVersion seen, a watch can resume from here
+    pub(crate) resource_version: String,
+    pub(crate) objects: Vec<SharedObject>,
+}
+
+/// What the store knows about a persisted list without loading it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    context: String,
+    objects: usize,
+    bytes: u64,
+    /// Seconds since the Unix epoch
+    saved_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    // Write next to the target and rename, so a crash never leaves half a file behind
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// Watched lists saved in the app cache directory, one file per list.
+/// Lists expire after [MAX_AGE], and the directory is kept under [MAX_BYTES].
+#[derive(Clone, Debug)]
+pub(crate) struct WatchCacheStore {
+    dir: PathBuf,
+    /// Held while writing to the directory, lists are saved from several threads
+    index: Arc<Mutex<HashMap<String, IndexEntry>>>,
+}
+
+impl WatchCacheStore {
+    pub(crate) fn new(app: &AppHandle) -> Option<WatchCacheStore> {
+        let dir = app.path().app_cache_dir().ok()?.join(WATCH_CACHE_DIR);
+        let index = std::fs::read(dir.join(INDEX_FILE)).ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        let store = WatchCacheStore { dir, index: Arc::new(Mutex::new(index)) };
+        store.prune(None);
+        Some(store)
+    }
+
+    /// The object count of a persisted list, without loading it
+    pub(crate) fn list_size(&self, id: &str) -> Option<usize> {
+        self.index.lock().ok()?.get(id).map(|entry| entry.objects)
+    }
+
+    /// Deletes expired lists, the oldest ones past the size limit, files no list owns,
+    /// and with `contexts`, the lists of every other context
+    pub(crate) fn prune(&self, contexts: Option<&HashSet<String>>) {
+        let Ok(mut index) = self.index.lock() else { return };
+        let now = now_secs();
+        index.retain(|_, entry| {
+            now.saturating_sub(entry.saved_at) < MAX_AGE.as_secs() && contexts.is_none_or(|c| c.contains(&entry.context))
+        });
+
+        let mut by_age: Vec<(u64, u64, String)> = index.iter().map(|(id, e)| (e.saved_at, e.bytes, id.clone())).collect();
+        by_age.sort();
+        let mut total: u64 = by_age.iter().map(|(_, bytes, _)| bytes).sum();
+        for (_, bytes, id) in by_age {
+            if total <= MAX_BYTES {
+                break;
+            }
+            total -= bytes;
+            index.remove(&id);
+        }
+
+        let keep: HashSet<PathBuf> = index.keys().map(|id| self.path(id)).chain([self.dir.join(INDEX_FILE)]).collect();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+        for path in entries.flatten().map(|entry| entry.path()).filter(|path| !keep.contains(path)) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                eprintln!("Failed to delete {}: {}", path.display(), e);
+            }
+        }
+        if let Err(e) = self.write_index(&index) {
+            eprintln!("Failed to write the watch cache index: {}", e);
+        }
+    }
+
+    fn write_index(&self, index: &HashMap<String, IndexEntry>) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let data = serde_json::to_vec(index).map_err(|e| e.to_string())?;
+        write_atomic(&self.dir.join(INDEX_FILE), &data)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(id)))
+    }
+
+    pub(crate) fn load(&self, id: &str) -> Option<PersistedList> {
+        let path = self.path(id);
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice::<PersistedList>(&data) {
+            Ok(list) if list.id == id => Some(list),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, list: &PersistedList) -> Result<(), String> {
+        let mut index = self.index.lock().map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let data = serde_json::to_vec(list).map_err(|e| e.to_string())?;
+        write_atomic(&self.path(&list.id), &data)?;
+
+        index.insert(list.id.clone(), IndexEntry {
+            context: list.context.clone(),
+            objects: list.objects.len(),
+            bytes: data.len() as u64,
+            saved_at: now_secs(),
+        });
+        self.write_index(&index)
+    }
+}
+
+/// Stable across builds, unlike `DefaultHasher`, so file names survive app updates
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Whether objects of a resource type may be written to disk.
+/// Secrets never are.
+pub(crate) fn persistable(group: &str, resource_plural: &str) -> bool {
+    !(group.is_empty() && resource_plural == "secrets")
+}
+
+/// Whether `candidate` is a later resourceVersion than `current`.
+/// resourceVersions are opaque, but every API server backed by etcd uses its revision number.
+/// Anything else is never considered newer, so lists from such servers aren't persisted.
+pub(crate) fn is_newer(current: Option<&str>, candidate: &str) -> bool {
+    let Ok(candidate) = candidate.parse::<u64>() else { return false };
+    match current.map(|c| c.parse::<u64>()) {
+        Some(Ok(current)) => candidate > current,
+        _ => true,
+    }
+}
@@ -14,12 +14,15 @@ interface WatcherDebugInfo {
     key: string;
     refCount: number;
     cacheSize: number;
+    memoryBytes: number;
+    idleForMs: number | null;
 }
 
 interface DebugInfo {
     open_tasks: number;
     tasks: TaskMetadata[];
     watchers: WatcherDebugInfo[];
+    listStrategies: Record<string, "streaming" | "paginated" | null>;
 }
 
 export function DebugMenu() {
@@ -80,7 +83,7 @@ export function DebugMenu() {
                             <li key={i} style={{ marginBottom: "0.25rem" }}>
                                 <div style={{ wordBreak: "break-all" }}>{w.key}</div>
                                 <div style={{ color: "#aaa", fontSize: "0.75rem" }}>
-                                    RefCount: {w.refCount} | Cache: {w.cacheSize}
+                                    RefCount: {w.refCount} | Cache: {w.cacheSize} ({(w.memoryBytes / 1024 / 1024).toFixed(1)} MB)
                                 </div>
                             </li>
                         ))}
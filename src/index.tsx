@@ -11,6 +11,7 @@ import { TauriImage } from "./util/tauri/TauriImage";
 import { ArrowRightIcon, GearIcon } from "@radix-ui/react-icons";
 import { useEffect, useMemo, useState } from "react";
 import { safeInvoke } from "./util/kube/requests";
+import { Channel } from "@tauri-apps/api/core";
 import { KubeConfig } from "@kubernetes/client-node";
 import { useNavigate } from "react-router";
 import { useAtom, useSetAtom } from "jotai";
@@ -24,11 +25,41 @@ export type KubeConfigInfo = {
   merged?: KubeConfig;
   sources: {
     contexts: string[];
-    origin: {
-      type: "environmentOrDefaultPath";
-    };
+    origin:
+      | { type: "environmentOrDefaultPath" }
+      | { type: "script"; info: { name: string; command: string } }
+      | { type: "file"; info: { path: string } }
+      | { type: "directory"; info: { path: string } };
+    error?: KubeConfigSourceError;
+    conflicts: { kind: "context" | "cluster" | "user"; name: string }[];
+    details: {
+      name: string;
+      flavour: ClusterFlavour;
+      server?: string;
+      serverVersion?: string;
+    }[];
   }[];
 };
+export type ClusterFlavour =
+  | "eks"
+  | "gke"
+  | "aks"
+  | "openShift"
+  | "k3s"
+  | "kind"
+  | "minikube"
+  | "generic";
+export type KubeConfigSourceError =
+  | { type: "spawn"; info: { message: string } }
+  | { type: "timeout"; info: { timeoutSecs: number } }
+  | { type: "exit"; info: { code?: number; stderr: string } }
+  | { type: "read"; info: { message: string } }
+  | { type: "parse"; info: { message: string } }
+  | { type: "merge"; info: { message: string } };
+export type KubeContextsEvent =
+  | { event: "contextsChanged"; data: { info: KubeConfigInfo } }
+  | { event: "clientRebuilt"; data: { contextName: string } }
+  | { event: "clientRebuildFailed"; data: { contextName: string; message: string } };
 export const DICEBEAR_STYLE = "glass";
 export const Home = () => {
   const navigate = useNavigate();
@@ -41,6 +72,14 @@ export const Home = () => {
         setContexts(result.data);
       }
     })();
+
+    const channel = new Channel<KubeContextsEvent>();
+    channel.onmessage = (msg) => {
+      if (msg.event === "contextsChanged") {
+        setContexts(msg.data.info);
+      }
+    };
+    safeInvoke("watch_kube_contexts", { channel });
   }, []);
 
   const setCurrentConfig = useSetAtom(currentConfigAtom);
@@ -101,6 +140,29 @@ export const Home = () => {
               </Box>
             </Flex>
 
+            {contexts && (contexts.merged?.contexts ?? []).length === 0 && (
+              <Box style={{ maxWidth: "360px" }}>
+                <Text as="div" size="3" weight="bold">
+                  No clusters found
+                </Text>
+                <Text as="div" size="2" color="gray">
+                  Kuboid couldn't find any Kubernetes contexts. Add a kubeconfig
+                  at ~/.kube/config, set the KUBECONFIG environment variable, or
+                  configure an extra kubeconfig source in settings.
+                </Text>
+                {contexts.sources
+                  .filter((source) => source.error)
+                  .map((source, i) => (
+                    <Text as="div" size="1" color="red" key={i}>
+                      {source.error?.type === "exit"
+                        ? source.error.info.stderr
+                        : source.error?.type === "timeout"
+                        ? `Timed out after ${source.error.info.timeoutSecs}s`
+                        : source.error?.info.message}
+                    </Text>
+                  ))}
+              </Box>
+            )}
             <RadioCards.Root
               value={selectedContext}
               onValueChange={(e) => setSelectedContext(e)}
@@ -9,7 +9,8 @@ import { useSetAtom } from "jotai";
 import { kubernetesResourceAtom } from "./cache";
 import { makeKubePath, KubeUrlComponents } from "./routes";
 import { GenericKubernetesResource } from "./types";
-import { ResourceCacheEntry } from "./cache";
+import { ResourceCacheEntry, TableColumnDefinition, WatchState } from "./cache";
+import { applyJsonPatch, JsonPatchOperation } from "./patch";
 
 const updateResourceInList = (list: GenericKubernetesResource[], newItem: GenericKubernetesResource): GenericKubernetesResource[] => {
     const index = list.findIndex(r => r.metadata.uid === newItem.metadata.uid);
@@ -30,12 +31,32 @@ const getEntry = (map: { [key: string]: ResourceCacheEntry }, key: string): Reso
 }
 
 
-type InternalSubscriptionEvent<T> =
+export type InternalSubscriptionEvent<T> =
     | {
         event: "apply" | "delete" | "initApply";
         data: { resource: T };
     }
-    | { event: "init" | "initDone" };
+    | {
+        event: "patch";
+        data: { uid: string; ops: JsonPatchOperation[] };
+    }
+    | {
+        event: "state";
+        data: { state: WatchState };
+    }
+    | {
+        event: "tableColumns";
+        data: { columns: TableColumnDefinition[] };
+    }
+    | {
+        event: "batch";
+        data: { events: InternalSubscriptionEvent<T>[] };
+    }
+    | {
+        event: "error";
+        data: { message: string };
+    }
+    | { event: "init" | "initDone" | "stale" | "live" };
 
 interface SubscriptionContextType {
     subscribe: (resource: KubeUrlComponents) => () => void;
@@ -55,12 +76,27 @@ export const useSubscriptionContext = () => {
 
 interface ActiveSubscription {
     count: number;
-    taskId: number;
+    // Allocated by the backend once start_listening returns
+    taskId: Promise<number>;
     channel: Channel<InternalSubscriptionEvent<GenericKubernetesResource>>;
     seenUids: Set<string>; // For stale-while-revalidate during init
     initializing: boolean;
+    // Set while waiting for the list to be sent again after a patch didn't apply
+    resyncing: boolean;
 }
 
+// Our copy of the list no longer matches the backend's, ask for the whole list again
+const requestResync = (sub: ActiveSubscription) => {
+    if (sub.resyncing) return;
+    sub.resyncing = true;
+    sub.taskId
+        .then((subscriptionId) => invoke("resync_subscription", { subscriptionId }))
+        .catch((err) => {
+            sub.resyncing = false;
+            console.error("Failed to resync subscription", err);
+        });
+};
+
 export const ResourceSubscriptionProvider = ({
     children,
 }: {
@@ -80,14 +116,48 @@ export const ResourceSubscriptionProvider = ({
             if (!sub) return;
 
             switch (event.event) {
+                case "batch":
+                    // React batches the state updates, so the whole batch renders once
+                    event.data.events.forEach((e) => handleEvent(key, e));
+                    break;
+
                 case "init":
-                    sub.initializing = true;
-                    sub.seenUids.clear();
                     sub.initializing = true;
                     sub.seenUids.clear();
                     setAllResources((prev) => {
                         const current = prev[key] || { resources: [], isLoading: false };
-                        return { ...prev, [key]: { ...current, isLoading: true } };
+                        return { ...prev, [key]: { ...current, isLoading: true, isStale: false } };
+                    });
+                    break;
+
+                case "state":
+                    setAllResources((prevCache) => {
+                        const currentEntry = getEntry(prevCache, key);
+                        return {
+                            ...prevCache,
+                            [key]: { ...currentEntry, watchState: event.data.state },
+                        };
+                    });
+                    break;
+
+                case "tableColumns":
+                    setAllResources((prevCache) => {
+                        const currentEntry = getEntry(prevCache, key);
+                        return {
+                            ...prevCache,
+                            [key]: { ...currentEntry, tableColumns: event.data.columns },
+                        };
+                    });
+                    break;
+
+                case "stale":
+                case "live":
+                    setAllResources((prevCache) => {
+                        const currentEntry = getEntry(prevCache, key);
+                        return {
+                            ...prevCache,
+                            [key]: { ...currentEntry, isStale: event.event === "stale" },
+                        };
                     });
                     break;
 
@@ -105,6 +175,7 @@ export const ResourceSubscriptionProvider = ({
 
                 case "initDone":
                     sub.initializing = false;
+                    sub.resyncing = false;
                     setAllResources((prevCache) => {
                         const currentEntry = getEntry(prevCache, key);
                         const filtered = currentEntry.resources.filter((r) =>
@@ -129,6 +200,34 @@ export const ResourceSubscriptionProvider = ({
                     });
                     break;
 
+                case "patch": {
+                    // The atom setter runs the updater right away, so this is known once it returns
+                    let resync = false;
+                    setAllResources((prevCache) => {
+                        const currentEntry = getEntry(prevCache, key);
+                        const existing = currentEntry.resources.find((r) => r.metadata.uid === event.data.uid);
+                        if (!existing) {
+                            resync = true;
+                            return prevCache;
+                        }
+                        let patched: GenericKubernetesResource;
+                        try {
+                            patched = applyJsonPatch(existing, event.data.ops);
+                        } catch (e) {
+                            console.error("Failed to apply patch, resyncing", event.data.uid, e);
+                            resync = true;
+                            return prevCache;
+                        }
+                        const newList = updateResourceInList(currentEntry.resources, patched);
+                        return {
+                            ...prevCache,
+                            [key]: { ...currentEntry, resources: newList },
+                        };
+                    });
+                    if (resync) requestResync(sub);
+                    break;
+                }
+
                 case "delete":
                     setAllResources((prevCache) => {
                         const currentEntry = getEntry(prevCache, key);
@@ -155,17 +254,30 @@ export const ResourceSubscriptionProvider = ({
                 // No, the atom is persistent.
             } else {
                 // Start new subscription
-                const taskId = Math.floor(Math.random() * 99999999);
                 const channel = new Channel<
                     InternalSubscriptionEvent<GenericKubernetesResource>
                 >();
 
+                console.log("Starting global subscription", key);
+                const taskId = invoke<number>("start_listening", {
+                    ...resource,
+                    apiVersion: resource.api_version,
+                    resourcePlural: resource.resource_plural,
+                    channel,
+                    namespaces: resource.namespaces,
+                    labelSelector: resource.labelSelector,
+                    fieldSelector: resource.fieldSelector,
+                    metadataOnly: resource.metadataOnly,
+                    asTable: resource.asTable,
+                });
+
                 const newSub: ActiveSubscription = {
                     count: 1,
                     taskId,
                     channel,
                     seenUids: new Set(),
                     initializing: true,
+                    resyncing: false,
                 };
                 subscriptions.current.set(key, newSub);
                 subscriptions.current.set(key, newSub);
@@ -179,27 +291,16 @@ export const ResourceSubscriptionProvider = ({
                     handleEvent(key, msg);
                 };
 
-                console.log("Starting global subscription", key, taskId);
-                invoke("start_listening", {
-                    ...resource,
-                    apiVersion: resource.api_version,
-                    resourcePlural: resource.resource_plural,
-                    channel,
-                    subscriptionId: taskId,
-                    namespaces: resource.namespaces,
-                    // name/namespace handled by spread? check args.
-                    // lib.rs: group, api_version, resource_plural, subscription_id, name?, namespace?, channel
-                    // resource components has: group, api_version, resource_plural, namespace?, name?
-                    // We need to match arguments specifically if proper fields aren't in `resource`.
-                    // `KubeUrlComponents` has exactly these fields.
-                }).catch((err) => {
+                taskId.catch((err) => {
                     console.error("Failed to start listening", err);
                     setAllResources((prev) => {
                         const current = prev[key] || { resources: [], isLoading: false };
                         return { ...prev, [key]: { ...current, isLoading: false } };
                     });
                     // Cleanup?
-                    subscriptions.current.delete(key);
+                    if (subscriptions.current.get(key) === newSub) {
+                        subscriptions.current.delete(key);
+                    }
                 });
             }
 
@@ -210,8 +311,11 @@ export const ResourceSubscriptionProvider = ({
 
                 currentSub.count--;
                 if (currentSub.count <= 0) {
-                    console.log("Stopping global subscription", key, currentSub.taskId);
-                    invoke("stop_listen_task", { taskId: currentSub.taskId });
+                    console.log("Stopping global subscription", key);
+                    // A failed start has nothing to stop
+                    currentSub.taskId
+                        .then((taskId) => invoke("stop_listen_task", { taskId }))
+                        .catch(() => { });
                     currentSub.channel.onmessage = () => { }; // No-op
                     subscriptions.current.delete(key);
                 }
@@ -7,8 +7,30 @@ import { GenericKubernetesResource } from "./types";
 export interface ResourceCacheEntry {
   resources: GenericKubernetesResource[];
   isLoading: boolean;
+  // Loaded from the backend's on-disk cache and not confirmed against the cluster yet
+  isStale?: boolean;
+  // Column definitions of an asTable subscription
+  tableColumns?: TableColumnDefinition[];
+  // Health of the backend watch, for live / reconnecting indicators
+  watchState?: WatchState;
 }
 
+export type WatchState =
+  | { state: "relisting" }
+  | { state: "connected" }
+  | { state: "backingOff"; info: { retryInMs: number; message: string } }
+  | { state: "proxyFailed"; info: { proxyUrl: string; retryInMs: number; message: string } }
+  | { state: "desynced" }
+  | { state: "forbidden"; info: { message: string } };
+
+export type TableColumnDefinition = {
+  name: string;
+  type: string;
+  format: string;
+  description: string;
+  priority: number;
+};
+
 export const kubernetesResourceAtom = atom<{
   [key: string]: ResourceCacheEntry;
 }>({});
@@ -0,0 +1,155 @@
+import { useEffect, useState } from "react";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { makeKubePath, type KubeUrlComponents } from "./routes";
+import { GenericKubernetesResource } from "./types";
+import { WatchState } from "./cache";
+import { applyJsonPatch } from "./patch";
+import { InternalSubscriptionEvent } from "./SubscriptionContext";
+
+type ComposedEvent<T> =
+  | { event: "fromContext"; data: { context: string; event: InternalSubscriptionEvent<T> } }
+  | { event: "batch"; data: { events: ComposedEvent<T>[] } };
+
+export type ComposedList<T = GenericKubernetesResource> = {
+  rows: { context: string; resource: T }[];
+  // Until every context has sent its first list
+  isLoading: boolean;
+  watchStates: { [context: string]: WatchState | undefined };
+  // Contexts that couldn't be watched, e.g. because connecting to them failed
+  errors: { [context: string]: string | undefined };
+};
+
+type ContextList<T> = {
+  resources: Map<string, T>;
+  seenUids: Set<string>;
+  initializing: boolean;
+  watchState?: WatchState;
+  error?: string;
+};
+
+/**
+ * Watches the same resource type across several contexts, as one list of rows tagged with their context.
+ * A context that gets disconnected empties out, the others carry on.
+ */
+export const useComposedResourceList = <T extends GenericKubernetesResource>(
+  resource: KubeUrlComponents,
+  contexts: string[]
+) => {
+  const [list, setList] = useState<ComposedList<T>>({ rows: [], isLoading: true, watchStates: {}, errors: {} });
+  const key = makeKubePath(resource);
+  const contextsKey = contexts.join("\n");
+
+  useEffect(() => {
+    setList({ rows: [], isLoading: true, watchStates: {}, errors: {} });
+    const lists = new Map<string, ContextList<T>>(
+      contexts.map((context) => [context, { resources: new Map(), seenUids: new Set(), initializing: true }])
+    );
+    let resyncing = false;
+
+    const channel = new Channel<ComposedEvent<T>>();
+    const id = invoke<number>("start_listening_composed", {
+      group: resource.group,
+      apiVersion: resource.api_version,
+      resourcePlural: resource.resource_plural,
+      contexts,
+      namespaces: resource.namespaces,
+      labelSelector: resource.labelSelector,
+      fieldSelector: resource.fieldSelector,
+      metadataOnly: resource.metadataOnly,
+      channel,
+    });
+
+    // Our copy of a list no longer matches the backend's, ask for all of them again
+    const requestResync = () => {
+      if (resyncing) return;
+      resyncing = true;
+      id.then((subscriptionId) => invoke("resync_subscription", { subscriptionId })).catch((err) => {
+        resyncing = false;
+        console.error("Failed to resync composed subscription", err);
+      });
+    };
+
+    const handleEvent = (context: string, event: InternalSubscriptionEvent<T>) => {
+      const list = lists.get(context);
+      if (!list) return;
+      switch (event.event) {
+        case "batch":
+          event.data.events.forEach((e) => handleEvent(context, e));
+          break;
+        case "init":
+          list.initializing = true;
+          list.seenUids.clear();
+          break;
+        case "initApply":
+          list.seenUids.add(event.data.resource.metadata.uid);
+          list.resources.set(event.data.resource.metadata.uid, event.data.resource);
+          break;
+        case "initDone":
+          for (const uid of list.resources.keys()) {
+            if (!list.seenUids.has(uid)) list.resources.delete(uid);
+          }
+          list.initializing = false;
+          resyncing = false;
+          break;
+        case "apply":
+          list.resources.set(event.data.resource.metadata.uid, event.data.resource);
+          break;
+        case "delete":
+          list.resources.delete(event.data.resource.metadata.uid);
+          break;
+        case "patch": {
+          const existing = list.resources.get(event.data.uid);
+          if (!existing) {
+            requestResync();
+            break;
+          }
+          try {
+            list.resources.set(event.data.uid, applyJsonPatch(existing, event.data.ops));
+          } catch (e) {
+            console.error("Failed to apply patch, resyncing", event.data.uid, e);
+            requestResync();
+          }
+          break;
+        }
+        case "state":
+          list.watchState = event.data.state;
+          break;
+        case "error":
+          list.error = event.data.message;
+          list.initializing = false;
+          break;
+      }
+    };
+
+    const handleMessage = (message: ComposedEvent<T>) => {
+      if (message.event === "batch") {
+        message.data.events.forEach(handleMessage);
+      } else {
+        handleEvent(message.data.context, message.data.event);
+      }
+    };
+
+    channel.onmessage = (message) => {
+      handleMessage(message);
+      setList({
+        rows: [...lists].flatMap(([context, list]) =>
+          [...list.resources.values()].map((resource) => ({ context, resource }))
+        ),
+        isLoading: [...lists.values()].some((list) => list.initializing),
+        watchStates: Object.fromEntries([...lists].map(([context, list]) => [context, list.watchState])),
+        errors: Object.fromEntries([...lists].map(([context, list]) => [context, list.error])),
+      });
+    };
+    id.catch((err) => {
+      console.error("Failed to start composed subscription", err);
+      setList((list) => ({ ...list, isLoading: false }));
+    });
+
+    return () => {
+      channel.onmessage = () => { };
+      id.then((taskId) => invoke("stop_listen_task", { taskId })).catch(() => { });
+    };
+  }, [key, contextsKey]);
+
+  return list;
+};
@@ -0,0 +1,93 @@
+// Applies RFC 6902 JSON patches sent by the backend in "patch" watch events.
+// Only the operations json_patch::diff produces are needed (add, remove, replace),
+// but move/copy/test are handled too so a hand-written patch doesn't silently break.
+
+export type JsonPatchOperation =
+  | { op: "add" | "replace" | "test"; path: string; value: any }
+  | { op: "remove"; path: string }
+  | { op: "move" | "copy"; from: string; path: string };
+
+const parsePointer = (pointer: string): string[] => {
+  if (pointer === "") return [];
+  return pointer
+    .slice(1)
+    .split("/")
+    .map((token) => token.replace(/~1/g, "/").replace(/~0/g, "~"));
+};
+
+const getAt = (doc: any, tokens: string[]): any => {
+  let current = doc;
+  for (const token of tokens) {
+    if (current === null || typeof current !== "object" || !(token in current)) {
+      throw new Error(`path not found: /${tokens.join("/")}`);
+    }
+    current = current[token];
+  }
+  return current;
+};
+
+// Returns a copy of `container` with `token` set, leaving the original untouched
+const withChild = (container: any, token: string, value: any, insert: boolean): any => {
+  if (Array.isArray(container)) {
+    const copy = [...container];
+    const index = token === "-" ? copy.length : Number(token);
+    if (insert) copy.splice(index, 0, value);
+    else copy[index] = value;
+    return copy;
+  }
+  return { ...container, [token]: value };
+};
+
+const withoutChild = (container: any, token: string): any => {
+  if (Array.isArray(container)) {
+    const copy = [...container];
+    copy.splice(Number(token), 1);
+    return copy;
+  }
+  const { [token]: _removed, ...rest } = container;
+  return rest;
+};
+
+// Rebuilds the objects along `tokens`, calling `update` on the parent of the last token
+const updateAt = (doc: any, tokens: string[], update: (parent: any, token: string) => any): any => {
+  if (tokens.length === 0) throw new Error("can't update the document root");
+  const [head, ...rest] = tokens;
+  if (rest.length === 0) return update(doc, head);
+  const child = doc?.[head];
+  if (child === null || typeof child !== "object") {
+    throw new Error(`path not found: ${head}`);
+  }
+  return withChild(doc, head, updateAt(child, rest, update), false);
+};
+
+const applyOperation = (doc: any, operation: JsonPatchOperation): any => {
+  const tokens = parsePointer(operation.path);
+  switch (operation.op) {
+    case "add":
+      if (tokens.length === 0) return operation.value;
+      return updateAt(doc, tokens, (parent, token) => withChild(parent, token, operation.value, true));
+    case "replace":
+      if (tokens.length === 0) return operation.value;
+      getAt(doc, tokens);
+      return updateAt(doc, tokens, (parent, token) => withChild(parent, token, operation.value, false));
+    case "remove":
+      getAt(doc, tokens);
+      return updateAt(doc, tokens, (parent, token) => withoutChild(parent, token));
+    case "test":
+      if (JSON.stringify(getAt(doc, tokens)) !== JSON.stringify(operation.value)) {
+        throw new Error(`test failed: ${operation.path}`);
+      }
+      return doc;
+    case "copy":
+      return applyOperation(doc, { op: "add", path: operation.path, value: getAt(doc, parsePointer(operation.from)) });
+    case "move": {
+      const value = getAt(doc, parsePointer(operation.from));
+      const removed = applyOperation(doc, { op: "remove", path: operation.from });
+      return applyOperation(removed, { op: "add", path: operation.path, value });
+    }
+  }
+};
+
+// Returns a patched copy of `doc`, throws if an operation doesn't apply
+export const applyJsonPatch = <T>(doc: T, ops: JsonPatchOperation[]): T =>
+  ops.reduce(applyOperation, doc);
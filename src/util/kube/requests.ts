@@ -12,6 +12,57 @@ export async function http<T>(
   }
 }
 
+export type CommandError =
+  | { type: "notConnected" }
+  | { type: "kubeconfigNotLoaded" }
+  | { type: "contextNotFound"; info: { message: string } }
+  | { type: "kubeconfigInvalid"; info: { message: string } }
+  | { type: "missingFile"; info: { path: string; message: string } }
+  | {
+      type: "execPlugin";
+      info: {
+        command?: string;
+        exitCode?: number;
+        stderr?: string;
+        message: string;
+      };
+    }
+  | { type: "auth"; info: { message: string } }
+  | { type: "clientConstruction"; info: { message: string } }
+  | { type: "proxy"; info: { proxyUrl: string; message: string } }
+  | { type: "api"; info: { code: number; reason: string; message: string } }
+  | { type: "invalidQuery"; info: { message: string } }
+  | { type: "subscriptionNotFound"; info: { id: number } }
+  | { type: "scriptNotConfirmed"; info: { command: string } }
+  | { type: "other"; info: { message: string } };
+
+export function formatCommandError(e: unknown): string {
+  if (typeof e === "object" && e !== null && "type" in e) {
+    const error = e as CommandError;
+    switch (error.type) {
+      case "notConnected":
+        return "Not connected to a cluster";
+      case "kubeconfigNotLoaded":
+        return "Kubeconfig hasn't been loaded yet";
+      case "missingFile":
+        return `Can't read ${error.info.path}: ${error.info.message}`;
+      case "execPlugin":
+        return error.info.stderr || error.info.message;
+      case "proxy":
+        return `Proxy ${error.info.proxyUrl} failed: ${error.info.message}`;
+      case "invalidQuery":
+        return `Invalid query: ${error.info.message}`;
+      case "subscriptionNotFound":
+        return `Subscription ${error.info.id} has already ended`;
+      case "scriptNotConfirmed":
+        return `Running ${error.info.command} wasn't allowed`;
+      default:
+        return error.info.message;
+    }
+  }
+  return String(e);
+}
+
 export async function safeInvoke<T>(
   command: string,
   args?: Record<string, unknown>
@@ -23,7 +74,7 @@ export async function safeInvoke<T>(
     }
     return { success: true, data: data as T };
   } catch (e) {
-    return { success: false, error: String(e) };
+    return { success: false, error: formatCommandError(e) };
   }
 }
 
@@ -47,6 +98,6 @@ export async function detailResource<T>(
     }
     return { success: true, data: data as T };
   } catch (e) {
-    return { success: false, error: String(e) };
+    return { success: false, error: formatCommandError(e) };
   }
 }
@@ -22,6 +22,16 @@ export interface KubeUrlComponents {
 
   // Optional list of namespaces to filter by (for multi-namespace watch)
   namespaces?: string[];
+
+  // Optional server-side selectors, i.e. "app=checkout" or "status.phase!=Running"
+  labelSelector?: string;
+  fieldSelector?: string;
+
+  // Only stream apiVersion, kind and metadata, for overviews of big resource types
+  metadataOnly?: boolean;
+
+  // Stream the rows and columns `kubectl get` would print, as { metadata, cells } objects
+  asTable?: boolean;
 } /**
  * returns a kube api path for the given components
  *
@@ -44,6 +54,14 @@ export const makeKubePath = (components: KubeUrlComponents) => {
     const nsSuffix = [...components.namespaces].sort().join(",");
     path += `?namespaces=${nsSuffix}`;
   }
+  const selectors = new URLSearchParams();
+  if (components.labelSelector) selectors.set("labelSelector", components.labelSelector);
+  if (components.fieldSelector) selectors.set("fieldSelector", components.fieldSelector);
+  if (components.metadataOnly) selectors.set("metadataOnly", "true");
+  if (components.asTable) selectors.set("asTable", "true");
+  if (selectors.size > 0) {
+    path += `${path.includes("?") ? "&" : "?"}${selectors.toString()}`;
+  }
   return path;
 };
 export type KubeCoreGroupPathParams = {
@@ -0,0 +1,91 @@
+import { useEffect, useRef, useState } from "react";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { makeKubePath, type KubeUrlComponents } from "./routes";
+import { GenericKubernetesResource } from "./types";
+
+// Sorting, filtering and paging done by the backend over a watched list
+export type ListQuery = {
+  // JSONPath, i.e. "$.metadata.creationTimestamp"
+  sortBy?: string;
+  descending?: boolean;
+  // i.e. ".status.phase != Running && nginx"
+  filter?: string;
+  offset?: number;
+  limit?: number;
+};
+
+export type QueryWindow<T = GenericKubernetesResource> = {
+  total: number;
+  offset: number;
+  rows: { context: string; resource: T }[];
+};
+
+/**
+ * Subscribes to a window of a watched list, the backend pushes it whenever a change affects it.
+ * Changing the query moves the window without restarting the watch.
+ */
+export const useResourceWindow = <T extends GenericKubernetesResource>(
+  resource: KubeUrlComponents & { context?: string },
+  query: ListQuery
+) => {
+  const [window, setWindow] = useState<QueryWindow<T> | null>(null);
+  // Allocated by the backend once start_listening_window returns
+  const taskId = useRef<number | null>(null);
+  const latestQuery = useRef(query);
+  latestQuery.current = query;
+  // The query the backend has, so the window isn't set twice on subscribe
+  const sentQueryKey = useRef<string | null>(null);
+  const key = makeKubePath(resource);
+  const queryKey = JSON.stringify(query);
+
+  useEffect(() => {
+    const channel = new Channel<QueryWindow<T>>();
+    channel.onmessage = setWindow;
+    let stopped = false;
+    const started = JSON.stringify(latestQuery.current);
+
+    const id = invoke<number>("start_listening_window", {
+      group: resource.group,
+      apiVersion: resource.api_version,
+      resourcePlural: resource.resource_plural,
+      namespace: resource.namespace,
+      namespaces: resource.namespaces,
+      labelSelector: resource.labelSelector,
+      fieldSelector: resource.fieldSelector,
+      metadataOnly: resource.metadataOnly,
+      context: resource.context,
+      query: latestQuery.current,
+      channel,
+    });
+    id.then((id) => {
+      if (stopped) return;
+      taskId.current = id;
+      sentQueryKey.current = started;
+      // The query may have changed while subscribing
+      const current = JSON.stringify(latestQuery.current);
+      if (current !== started) {
+        sentQueryKey.current = current;
+        invoke("set_window_query", { subscriptionId: id, query: latestQuery.current }).catch((err) =>
+          console.error("Failed to update window", err)
+        );
+      }
+    }, (err) => console.error("Failed to start window subscription", err));
+
+    return () => {
+      stopped = true;
+      channel.onmessage = () => { };
+      taskId.current = null;
+      id.then((taskId) => invoke("stop_listen_task", { taskId })).catch(() => { });
+    };
+  }, [key, resource.context]);
+
+  useEffect(() => {
+    if (taskId.current === null || sentQueryKey.current === queryKey) return;
+    sentQueryKey.current = queryKey;
+    invoke("set_window_query", { subscriptionId: taskId.current, query }).catch((err) =>
+      console.error("Failed to update window", err)
+    );
+  }, [queryKey]);
+
+  return window;
+};